@@ -0,0 +1,342 @@
+//! Heuristics to flag suspicious LNK files.
+//!
+//! Every heuristic produces a `Finding` with a severity and the rationale behind it.
+//! The heuristics are based on techniques seen in malicious LNK files (LOLBin targets,
+//! hidden arguments, fake icons, appended payloads, etc) and include the timestamp anomalies.
+
+use crate::{shell_link_header::ShowCommandOptions, LNKParser};
+use serde::Serialize;
+use std::fmt::{self, Display};
+
+/// The maximum number of characters the shortcut properties dialog shows for the target and arguments.
+pub const VISIBLE_ARGUMENTS_LIMIT: usize = 260;
+
+/// The minimum run of whitespace characters considered as padding.
+const WHITESPACE_PADDING_LIMIT: usize = 10;
+
+/// Living off the land binaries commonly abused as LNK targets.
+const LOLBINS: &[&str] = &[
+    "powershell.exe",
+    "pwsh.exe",
+    "powershell_ise.exe",
+    "mshta.exe",
+    "cmd.exe",
+    "rundll32.exe",
+    "regsvr32.exe",
+    "wscript.exe",
+    "cscript.exe",
+    "certutil.exe",
+    "bitsadmin.exe",
+    "msiexec.exe",
+    "forfiles.exe",
+    "conhost.exe",
+    "schtasks.exe",
+    "wmic.exe",
+];
+
+/// Arguments that indicate encoded or obfuscated PowerShell.
+const POWERSHELL_INDICATORS: &[&str] = &[
+    "-enc ",
+    "-encodedcommand",
+    "-ec ",
+    "-e ",
+    "frombase64string",
+    "invoke-expression",
+    "iex(",
+    "iex (",
+    "-windowstyle hidden",
+    "-w hidden",
+    "-w 1",
+    "downloadstring",
+    "downloadfile",
+    "[char]",
+    "-join",
+    "-bxor",
+    "gzipstream",
+];
+
+/// Icon file types that are used to disguise the shortcut as a document.
+const DOCUMENT_EXTENSIONS: &[&str] = &[
+    "pdf", "doc", "docx", "xls", "xlsx", "ppt", "pptx", "rtf", "txt", "jpg", "jpeg", "png", "gif",
+    "bmp", "zip", "rar", "7z", "mp3", "mp4", "html", "htm",
+];
+
+/// How suspicious a finding is.
+#[derive(Debug, Serialize, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Severity {
+    Info,
+    Low,
+    Medium,
+    High,
+}
+
+impl Display for Severity {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{:?}", self)
+    }
+}
+
+/// The result of a heuristic that matched.
+#[derive(Debug, Serialize, Clone, PartialEq, Eq)]
+pub struct Finding {
+    /// A stable identifier of the heuristic (e.g. `lolbin_target`).
+    pub id: &'static str,
+    pub severity: Severity,
+    /// Why the heuristic matched.
+    pub rationale: String,
+}
+
+impl Finding {
+    pub(crate) fn new(id: &'static str, severity: Severity, rationale: String) -> Self {
+        Self {
+            id,
+            severity,
+            rationale,
+        }
+    }
+}
+
+impl LNKParser {
+    /// Run the built-in heuristics against the parsed file. The findings are sorted by severity (highest first).
+    pub fn analyze(&self) -> Vec<Finding> {
+        let mut findings = vec![];
+        let header = self.get_shell_link_header();
+        let target = self.target_path();
+        let arguments = self
+            .get_command_line_arguments()
+            .as_ref()
+            .map(|s| s.to_string())
+            .unwrap_or_default();
+
+        // LOLBin targets
+        if let Some(target) = &target {
+            let name = file_name(target).to_lowercase();
+            if LOLBINS.contains(&name.as_str()) {
+                findings.push(Finding::new(
+                    "lolbin_target",
+                    Severity::Medium,
+                    format!(
+                        "the target '{}' is a LOLBin commonly abused to run commands",
+                        target
+                    ),
+                ));
+            }
+        }
+        let lower_arguments = arguments.to_lowercase();
+        let chained: Vec<&str> = LOLBINS
+            .iter()
+            .copied()
+            .filter(|lolbin| {
+                let name = lolbin.trim_end_matches(".exe");
+                lower_arguments
+                    .split(|c: char| c.is_whitespace() || "\"'&|;,\\/()".contains(c))
+                    .any(|word| word == *lolbin || word == name)
+            })
+            .collect();
+        if !chained.is_empty() {
+            findings.push(Finding::new(
+                "lolbin_in_arguments",
+                Severity::Medium,
+                format!("the arguments invoke the LOLBin(s) {}", chained.join(", ")),
+            ));
+        }
+
+        // Hidden arguments
+        let arguments_length = arguments.chars().count();
+        if arguments_length > VISIBLE_ARGUMENTS_LIMIT {
+            findings.push(Finding::new(
+                "long_arguments",
+                Severity::Medium,
+                format!(
+                    "the arguments are {} characters long, the properties dialog only shows the first {}",
+                    arguments_length, VISIBLE_ARGUMENTS_LIMIT
+                ),
+            ));
+        }
+        let padding = longest_whitespace_run(&arguments);
+        if padding >= WHITESPACE_PADDING_LIMIT {
+            findings.push(Finding::new(
+                "whitespace_padding",
+                Severity::High,
+                format!(
+                    "the arguments contain {} consecutive whitespace characters used to push the payload out of view",
+                    padding
+                ),
+            ));
+        }
+
+        // Encoded/obfuscated PowerShell
+        let is_powershell = target
+            .as_ref()
+            .map(|t| file_name(t).to_lowercase())
+            .map(|name| name.starts_with("powershell") || name.starts_with("pwsh"))
+            .unwrap_or(false)
+            || lower_arguments.contains("powershell")
+            || lower_arguments.contains("pwsh");
+        if is_powershell {
+            // pad the arguments to match the indicators at the end of the string
+            let padded_arguments = format!("{} ", lower_arguments);
+            let indicators: Vec<&str> = POWERSHELL_INDICATORS
+                .iter()
+                .copied()
+                .filter(|indicator| padded_arguments.contains(indicator))
+                .map(|indicator| indicator.trim())
+                .collect();
+            let has_base64_blob = arguments
+                .split(|c: char| c.is_whitespace() || c == '\'' || c == '"')
+                .any(is_base64_blob);
+            let backticks = arguments.matches('`').count();
+            let mut reasons = vec![];
+            if !indicators.is_empty() {
+                reasons.push(format!("indicators: {}", indicators.join(", ")));
+            }
+            if has_base64_blob {
+                reasons.push(String::from("a base64 encoded blob"));
+            }
+            if backticks > 2 {
+                reasons.push(format!("{} backtick escapes", backticks));
+            }
+            if !reasons.is_empty() {
+                findings.push(Finding::new(
+                    "obfuscated_powershell",
+                    Severity::High,
+                    format!("PowerShell is invoked with {}", reasons.join("; ")),
+                ));
+            }
+        }
+
+        // Icon from a different file type
+        if let Some(icon_location) = self.get_icon_location() {
+            let icon_location = icon_location.to_string();
+            if let Some(icon_extension) = extension(&icon_location) {
+                let target_extension = target.as_deref().and_then(extension);
+                if DOCUMENT_EXTENSIONS.contains(&icon_extension.as_str())
+                    && target_extension.as_ref() != Some(&icon_extension)
+                {
+                    findings.push(Finding::new(
+                        "icon_mismatch",
+                        Severity::Medium,
+                        format!(
+                            "the icon is taken from a '.{}' file while the target is {}",
+                            icon_extension,
+                            match target_extension {
+                                Some(target_extension) => format!("a '.{}' file", target_extension),
+                                None => String::from("unknown"),
+                            }
+                        ),
+                    ));
+                }
+            }
+        }
+
+        // Environment variable target without LinkInfo
+        let has_environment_block = self
+            .get_extra_data()
+            .as_ref()
+            .map(|extra_data| {
+                extra_data
+                    .blocks
+                    .iter()
+                    .any(|block| block.name() == "EnvironmentVariableDataBlock")
+            })
+            .unwrap_or(false);
+        if self.get_link_info().is_none() && (header.flags.HasExpString || has_environment_block) {
+            findings.push(Finding::new(
+                "environment_variable_target",
+                Severity::Medium,
+                String::from("the LinkInfo structure is missing and the target is resolved from an environment variable path"),
+            ));
+        }
+
+        // Appended overlay
+        let overlay_size = self.overlay_size();
+        if overlay_size > 0 {
+            findings.push(Finding::new(
+                "appended_overlay",
+                Severity::High,
+                format!(
+                    "{} bytes are appended after the end of the LNK structures (offset 0x{:X})",
+                    overlay_size,
+                    self.get_parsed_size()
+                ),
+            ));
+        }
+
+        // Non-standard header fields
+        let mut non_standard = vec![];
+        if let Err(e) = header.validate() {
+            non_standard.push(e.to_string());
+        }
+        for field in &header.non_zero_reserved_fields {
+            non_standard.push(format!("{} is not zero", field));
+        }
+        if let ShowCommandOptions::UNKNOWN(value) = header.show_command {
+            non_standard.push(format!("unknown show command 0x{:X}", value));
+        }
+        if !non_standard.is_empty() {
+            findings.push(Finding::new(
+                "non_standard_header",
+                Severity::Medium,
+                format!(
+                    "the ShellLinkHeader has non-standard values: {}",
+                    non_standard.join(", ")
+                ),
+            ));
+        }
+
+        findings.extend(self.timestamp_anomalies());
+
+        findings.sort_by_key(|finding| std::cmp::Reverse(finding.severity));
+        findings
+    }
+
+    /// The target path, falling back to the relative path when the absolute path is not available.
+    fn target_path(&self) -> Option<String> {
+        self.get_target_full_path().to_owned().or_else(|| {
+            self.get_relative_path()
+                .as_ref()
+                .map(|relative_path| relative_path.to_string())
+        })
+    }
+}
+
+/// Returns the last component of a Windows or Unix path.
+fn file_name(path: &str) -> &str {
+    path.trim_matches('"')
+        .rsplit(['\\', '/'])
+        .next()
+        .unwrap_or(path)
+}
+
+/// Returns the lowercase extension of a path.
+fn extension(path: &str) -> Option<String> {
+    let name = file_name(path);
+    let (_, extension) = name.rsplit_once('.')?;
+    if extension.is_empty() {
+        None
+    } else {
+        Some(extension.trim_end_matches('"').to_lowercase())
+    }
+}
+
+fn longest_whitespace_run(s: &str) -> usize {
+    let mut longest = 0;
+    let mut current = 0;
+    for c in s.chars() {
+        if c.is_whitespace() {
+            current += 1;
+            longest = longest.max(current);
+        } else {
+            current = 0;
+        }
+    }
+    longest
+}
+
+fn is_base64_blob(word: &str) -> bool {
+    word.len() >= 40
+        && word.len().is_multiple_of(4)
+        && word
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '+' || c == '/' || c == '=')
+}
@@ -0,0 +1,306 @@
+//! The CSV dialect (RFC 4180 quoting, delimiter and line endings) and text encoding of the CSV outputs.
+
+use serde::de::{Deserialize, Deserializer, MapAccess, SeqAccess, Visitor};
+use serde::Serialize;
+use serde_json::Number;
+use std::{fmt, io};
+
+/// The delimiter, line ending and encoding of the CSV outputs.
+#[derive(Debug, Clone, Copy)]
+pub struct CsvFormat {
+    pub delimiter: u8,
+    pub crlf: bool,
+    pub encoding: Encoding,
+}
+
+impl Default for CsvFormat {
+    fn default() -> Self {
+        Self {
+            delimiter: b',',
+            crlf: true,
+            encoding: Encoding::Utf8,
+        }
+    }
+}
+
+impl CsvFormat {
+    /// A CSV row with all the values quoted, the quotes in the values are doubled.
+    pub fn row<S: AsRef<str>>(&self, values: &[S]) -> io::Result<String> {
+        let terminator = if self.crlf {
+            csv::Terminator::CRLF
+        } else {
+            csv::Terminator::Any(b'\n')
+        };
+        let mut writer = csv::WriterBuilder::new()
+            .delimiter(self.delimiter)
+            .terminator(terminator)
+            .quote_style(csv::QuoteStyle::Always)
+            .from_writer(vec![]);
+        writer.write_record(values.iter().map(|value| value.as_ref()))?;
+        let row = writer.into_inner().map_err(|e| e.into_error())?;
+        String::from_utf8(row).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))
+    }
+}
+
+/// The text encoding of the output.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Encoding {
+    Utf8,
+    /// UTF-8 with a byte order mark, opens correctly in Excel.
+    Utf8Bom,
+    Utf16Le,
+}
+
+impl Encoding {
+    pub fn from_str(s: &str) -> Encoding {
+        match s {
+            "utf-8-bom" => Encoding::Utf8Bom,
+            "utf-16le" => Encoding::Utf16Le,
+            _ => Encoding::Utf8,
+        }
+    }
+
+    /// The byte order mark written at the start of the output.
+    pub fn bom(&self) -> &'static [u8] {
+        match self {
+            Encoding::Utf8 => &[],
+            Encoding::Utf8Bom => &[0xEF, 0xBB, 0xBF],
+            Encoding::Utf16Le => &[0xFF, 0xFE],
+        }
+    }
+
+    pub fn encode(&self, data: &str) -> Vec<u8> {
+        match self {
+            Encoding::Utf8 | Encoding::Utf8Bom => data.as_bytes().to_vec(),
+            Encoding::Utf16Le => data.encode_utf16().flat_map(u16::to_le_bytes).collect(),
+        }
+    }
+}
+
+/// Flatten the serialized data to `(name, value)` pairs, the names of the nested fields are joined
+/// with dots (e.g. `shell_link_header.file_size`). Arrays of objects use the index as the field name
+/// and the arrays of scalars are joined with commas. Null fields are skipped. The fields are in the
+/// order they are serialized (the structure order of the parsed data).
+pub fn flatten<T: Serialize>(data: &T) -> serde_json::Result<Vec<(String, String)>> {
+    // the JSON text keeps the serialization order, `serde_json::Value` objects are sorted by key
+    let json = serde_json::to_string(data)?;
+    let value = FlatValue::deserialize(&mut serde_json::Deserializer::from_str(&json))?;
+    let mut fields = vec![];
+    flatten_into(&mut fields, String::new(), value);
+    Ok(fields)
+}
+
+/// A JSON value with the object fields in the order they are read.
+enum FlatValue {
+    Null,
+    Scalar(String),
+    Object(Vec<(String, FlatValue)>),
+    Array(Vec<FlatValue>),
+}
+
+impl FlatValue {
+    fn is_nested(&self) -> bool {
+        matches!(self, FlatValue::Object(_) | FlatValue::Array(_))
+    }
+
+    fn into_scalar(self) -> String {
+        match self {
+            FlatValue::Scalar(value) => value,
+            _ => String::new(),
+        }
+    }
+}
+
+impl<'de> Deserialize<'de> for FlatValue {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        deserializer.deserialize_any(FlatValueVisitor)
+    }
+}
+
+struct FlatValueVisitor;
+
+impl<'de> Visitor<'de> for FlatValueVisitor {
+    type Value = FlatValue;
+
+    fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        formatter.write_str("a JSON value")
+    }
+
+    fn visit_unit<E>(self) -> Result<FlatValue, E> {
+        Ok(FlatValue::Null)
+    }
+
+    fn visit_bool<E>(self, v: bool) -> Result<FlatValue, E> {
+        Ok(FlatValue::Scalar(v.to_string()))
+    }
+
+    fn visit_i64<E>(self, v: i64) -> Result<FlatValue, E> {
+        Ok(FlatValue::Scalar(v.to_string()))
+    }
+
+    fn visit_u64<E>(self, v: u64) -> Result<FlatValue, E> {
+        Ok(FlatValue::Scalar(v.to_string()))
+    }
+
+    fn visit_f64<E>(self, v: f64) -> Result<FlatValue, E> {
+        Ok(FlatValue::Scalar(
+            Number::from_f64(v)
+                .map(|n| n.to_string())
+                .unwrap_or_default(),
+        ))
+    }
+
+    fn visit_str<E>(self, v: &str) -> Result<FlatValue, E> {
+        Ok(FlatValue::Scalar(v.to_owned()))
+    }
+
+    fn visit_string<E>(self, v: String) -> Result<FlatValue, E> {
+        Ok(FlatValue::Scalar(v))
+    }
+
+    fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<FlatValue, A::Error> {
+        let mut values = vec![];
+        while let Some(value) = seq.next_element()? {
+            values.push(value);
+        }
+        Ok(FlatValue::Array(values))
+    }
+
+    fn visit_map<A: MapAccess<'de>>(self, mut map: A) -> Result<FlatValue, A::Error> {
+        let mut fields = vec![];
+        while let Some(field) = map.next_entry()? {
+            fields.push(field);
+        }
+        Ok(FlatValue::Object(fields))
+    }
+}
+
+fn flatten_into(fields: &mut Vec<(String, String)>, name: String, value: FlatValue) {
+    let child_name = |child: &str| {
+        if name.is_empty() {
+            child.to_string()
+        } else {
+            format!("{}.{}", name, child)
+        }
+    };
+    match value {
+        FlatValue::Null => {}
+        FlatValue::Object(map) => {
+            for (key, value) in map {
+                flatten_into(fields, child_name(&key), value);
+            }
+        }
+        FlatValue::Array(values) if values.iter().any(FlatValue::is_nested) => {
+            for (i, value) in values.into_iter().enumerate() {
+                flatten_into(fields, child_name(&i.to_string()), value);
+            }
+        }
+        FlatValue::Array(values) => {
+            let values = values
+                .into_iter()
+                .map(FlatValue::into_scalar)
+                .collect::<Vec<String>>();
+            fields.push((name, values.join(",")));
+        }
+        FlatValue::Scalar(value) => fields.push((name, value)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_row_quotes() {
+        let format = CsvFormat::default();
+        assert_eq!(
+            format.row(&["-c \"Write-Host\"", "a,b", ""]).unwrap(),
+            "\"-c \"\"Write-Host\"\"\",\"a,b\",\"\"\r\n"
+        );
+        // new lines are kept inside the quoted value
+        assert_eq!(format.row(&["a\r\nb"]).unwrap(), "\"a\r\nb\"\r\n");
+    }
+
+    #[test]
+    fn test_row_delimiter_and_line_ending() {
+        let format = CsvFormat {
+            delimiter: b'\t',
+            crlf: false,
+            ..CsvFormat::default()
+        };
+        assert_eq!(format.row(&["a", "b\tc"]).unwrap(), "\"a\"\t\"b\tc\"\n");
+        let format = CsvFormat {
+            crlf: true,
+            ..format
+        };
+        assert_eq!(format.row(&["a", "b"]).unwrap(), "\"a\"\t\"b\"\r\n");
+    }
+
+    #[test]
+    fn test_encoding() {
+        assert_eq!(Encoding::from_str("utf-8"), Encoding::Utf8);
+        assert!(Encoding::Utf8.bom().is_empty());
+        assert_eq!(Encoding::from_str("utf-8-bom").bom(), [0xEF, 0xBB, 0xBF]);
+        assert_eq!(Encoding::Utf8Bom.encode("é"), "é".as_bytes());
+
+        let utf16 = Encoding::from_str("utf-16le");
+        assert_eq!(utf16.bom(), [0xFF, 0xFE]);
+        assert_eq!(
+            utf16.encode("a,é\n"),
+            [0x61, 0x00, 0x2C, 0x00, 0xE9, 0x00, 0x0A, 0x00]
+        );
+        // characters outside the BMP are surrogate pairs
+        assert_eq!(utf16.encode("😀"), [0x3D, 0xD8, 0x00, 0xDE]);
+    }
+
+    #[derive(Serialize)]
+    struct Header {
+        file_size: u32,
+        flags: Vec<&'static str>,
+    }
+
+    #[derive(Serialize)]
+    struct Item {
+        name: &'static str,
+    }
+
+    #[derive(Serialize)]
+    struct Parsed {
+        shell_link_header: Header,
+        name_string: Option<String>,
+        id_list: Vec<Item>,
+        arguments: &'static str,
+    }
+
+    #[test]
+    fn test_flatten() {
+        let parsed = Parsed {
+            shell_link_header: Header {
+                file_size: 4,
+                flags: vec!["HasLinkInfo", "IsUnicode"],
+            },
+            name_string: None,
+            id_list: vec![Item { name: "C:\\" }, Item { name: "test.txt" }],
+            arguments: "-c \"x\"",
+        };
+        // the fields are in the structure order, not sorted by name
+        assert_eq!(
+            flatten(&parsed).unwrap(),
+            vec![
+                ("shell_link_header.file_size".to_string(), "4".to_string()),
+                (
+                    "shell_link_header.flags".to_string(),
+                    "HasLinkInfo,IsUnicode".to_string()
+                ),
+                ("id_list.0.name".to_string(), "C:\\".to_string()),
+                ("id_list.1.name".to_string(), "test.txt".to_string()),
+                ("arguments".to_string(), "-c \"x\"".to_string()),
+            ]
+        );
+        assert_eq!(
+            flatten(&json!({"values": [1.5, true, null]})).unwrap(),
+            vec![("values".to_string(), "1.5,true,".to_string())]
+        );
+    }
+}
@@ -0,0 +1,136 @@
+//! The `dump` command, prints every structure of a LNK file with its offset, size, raw bytes and decoded value.
+
+use lnk_parser::{layout::Region, LNKParser};
+use serde_json::{json, Value};
+use std::fs;
+use std::io::{self, Write};
+
+/// The number of bytes per hex line.
+const HEX_LINE_SIZE: usize = 16;
+
+/// Parse the file and write its annotated structures to `w`, as text or as JSON.
+pub fn dump<W: Write>(w: &mut W, path: &str, validate: bool, json: bool) -> io::Result<()> {
+    let data = fs::read(path)?;
+    let parsed = if validate {
+        LNKParser::from_buffer(&data)
+    } else {
+        LNKParser::from_buffer_unchecked(&data)
+    }
+    .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e.to_string()))?;
+    let regions = parsed.layout();
+    if json {
+        let regions = regions
+            .iter()
+            .map(|region| region_json(region, &data))
+            .collect::<Vec<Value>>();
+        writeln!(w, "{}", serde_json::to_string_pretty(&regions)?)
+    } else {
+        let width = name_width(&regions, 0).max("Structure".len());
+        writeln!(
+            w,
+            "{:<10}  {:>6}  {:<width$}  Value",
+            "Offset", "Size", "Structure"
+        )?;
+        for region in &regions {
+            write_region(w, region, &data, 0, width)?;
+        }
+        Ok(())
+    }
+}
+
+/// The width of the structure column (the names are indented by their depth).
+fn name_width(regions: &[Region], depth: usize) -> usize {
+    regions
+        .iter()
+        .map(|region| (depth * 2 + region.name.len()).max(name_width(&region.children, depth + 1)))
+        .max()
+        .unwrap_or_default()
+}
+
+fn write_region<W: Write>(
+    w: &mut W,
+    region: &Region,
+    data: &[u8],
+    depth: usize,
+    width: usize,
+) -> io::Result<()> {
+    let name = format!("{}{}", "  ".repeat(depth), region.name);
+    writeln!(
+        w,
+        "0x{:08X}  {:>6}  {:<width$}  {}",
+        region.offset,
+        region.size,
+        name,
+        printable(&region.value)
+    )?;
+    if region.children.is_empty() {
+        // the raw bytes of the fields, the structures are covered by their fields
+        let indent = " ".repeat(20 + depth * 2);
+        for line in region.bytes(data).chunks(HEX_LINE_SIZE) {
+            let hex = line
+                .iter()
+                .map(|b| format!("{:02X}", b))
+                .collect::<Vec<String>>()
+                .join(" ");
+            let ascii = line
+                .iter()
+                .map(|b| match b {
+                    0x20..=0x7E => *b as char,
+                    _ => '.',
+                })
+                .collect::<String>();
+            writeln!(
+                w,
+                "{}{:<hex_width$}  |{}|",
+                indent,
+                hex,
+                ascii,
+                hex_width = HEX_LINE_SIZE * 3 - 1
+            )?;
+        }
+    }
+    for child in &region.children {
+        write_region(w, child, data, depth + 1, width)?;
+    }
+    Ok(())
+}
+
+/// The region with the hex encoded bytes of the fields.
+fn region_json(region: &Region, data: &[u8]) -> Value {
+    let mut value = json!({
+        "offset": region.offset,
+        "size": region.size,
+        "name": region.name,
+        "value": region.value,
+    });
+    if region.children.is_empty() {
+        value["hex"] = region
+            .bytes(data)
+            .iter()
+            .map(|b| format!("{:02x}", b))
+            .collect::<String>()
+            .into();
+    } else {
+        value["children"] = region
+            .children
+            .iter()
+            .map(|child| region_json(child, data))
+            .collect::<Vec<Value>>()
+            .into();
+    }
+    value
+}
+
+/// Escape the control characters so that a decoded value is printed on a single line.
+fn printable(value: &str) -> String {
+    value
+        .chars()
+        .map(|c| {
+            if c.is_control() {
+                c.escape_default().to_string()
+            } else {
+                c.to_string()
+            }
+        })
+        .collect()
+}
@@ -0,0 +1,300 @@
+//! Map the parsed files to the [Elastic Common Schema](https://www.elastic.co/guide/en/ecs/current/index.html).
+
+use crate::writers::{OutputRecord, RecordOptions};
+use chrono::{DateTime, Utc};
+use lnk_parser::LNKParser;
+use serde::Serialize;
+use std::collections::BTreeMap;
+
+/// The ECS version the records are mapped to.
+const ECS_VERSION: &str = "8.11.0";
+
+/// The `@timestamp` of the files without any time (no file system metadata and zeroed header times).
+const NO_TIMESTAMP: DateTime<Utc> = DateTime::UNIX_EPOCH;
+
+/// A parsed file mapped to the Elastic Common Schema, the fields without an ECS equivalent are under `lnk`.
+#[derive(Debug, Serialize)]
+pub struct EcsRecord {
+    #[serde(rename = "@timestamp")]
+    timestamp: DateTime<Utc>,
+    ecs: EcsVersion,
+    event: EcsEvent,
+    file: EcsFile,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    host: Option<EcsHost>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    process: Option<EcsProcess>,
+    lnk: OutputRecord,
+}
+
+#[derive(Debug, Serialize)]
+struct EcsVersion {
+    version: &'static str,
+}
+
+#[derive(Debug, Serialize)]
+struct EcsEvent {
+    kind: &'static str,
+    category: Vec<&'static str>,
+    #[serde(rename = "type")]
+    event_type: Vec<&'static str>,
+    module: &'static str,
+    dataset: &'static str,
+}
+
+#[derive(Debug, Serialize)]
+struct EcsFile {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    path: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    name: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    directory: Option<String>,
+    extension: &'static str,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    size: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    created: Option<DateTime<Utc>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    mtime: Option<DateTime<Utc>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    accessed: Option<DateTime<Utc>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    ctime: Option<DateTime<Utc>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    target_path: Option<String>,
+    #[serde(skip_serializing_if = "BTreeMap::is_empty")]
+    hash: BTreeMap<&'static str, String>,
+}
+
+#[derive(Debug, Serialize)]
+struct EcsHost {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    name: Option<String>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    mac: Vec<String>,
+}
+
+#[derive(Debug, Serialize)]
+struct EcsProcess {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    executable: Option<String>,
+    command_line: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    working_directory: Option<String>,
+}
+
+impl EcsRecord {
+    pub fn new(parsed: LNKParser, options: &RecordOptions) -> Self {
+        let record = parsed.to_normalized_record();
+        let lnk = OutputRecord::new(parsed, true, options);
+        let is_alert = lnk.findings.as_ref().is_some_and(|f| !f.is_empty())
+            || lnk.rule_matches.as_ref().is_some_and(|r| !r.is_empty());
+
+        let lnk_path = record.lnk_full_path.as_ref().map(std::path::Path::new);
+        let mut hash = BTreeMap::new();
+        for (name, value) in [
+            ("md5", &record.lnk_md5),
+            ("sha1", &record.lnk_sha1),
+            ("sha256", &record.lnk_sha256),
+        ]
+        .iter()
+        {
+            if let Some(value) = value {
+                hash.insert(*name, value.to_owned());
+            }
+        }
+        let file = EcsFile {
+            path: record.lnk_full_path.to_owned(),
+            name: lnk_path
+                .and_then(|p| p.file_name())
+                .map(|name| name.to_string_lossy().to_string()),
+            directory: lnk_path
+                .and_then(|p| p.parent())
+                .map(|parent| parent.to_string_lossy().to_string()),
+            extension: "lnk",
+            size: record.lnk_size,
+            created: record.lnk_creation_time,
+            mtime: record.lnk_modification_time,
+            accessed: record.lnk_access_time,
+            ctime: record.lnk_change_time,
+            target_path: record.target_full_path.to_owned(),
+            hash,
+        };
+
+        // ECS MAC addresses are uppercase and separated by hyphens
+        let mac = record
+            .mac_address
+            .iter()
+            .filter(|mac| mac.as_str() != "00:00:00:00:00:00")
+            .map(|mac| mac.replace(':', "-").to_uppercase())
+            .collect::<Vec<String>>();
+        let host_name = record.target_hostname.to_owned().filter(|h| !h.is_empty());
+        let host = if host_name.is_some() || !mac.is_empty() {
+            Some(EcsHost {
+                name: host_name,
+                mac,
+            })
+        } else {
+            None
+        };
+
+        let process = record
+            .command_line_arguments
+            .as_ref()
+            .map(|arguments| EcsProcess {
+                executable: record.target_full_path.to_owned(),
+                command_line: match &record.target_full_path {
+                    Some(target) if target.contains(' ') => format!("\"{}\" {}", target, arguments),
+                    Some(target) => format!("{} {}", target, arguments),
+                    None => arguments.to_owned(),
+                },
+                working_directory: record.working_dir.to_owned(),
+            });
+
+        Self {
+            timestamp: [
+                record.lnk_modification_time,
+                record.lnk_change_time,
+                record.lnk_creation_time,
+                record.lnk_access_time,
+                record.target_modification_time,
+                record.target_creation_time,
+                record.target_access_time,
+            ]
+            .iter()
+            .find_map(|time| *time)
+            .unwrap_or(NO_TIMESTAMP),
+            ecs: EcsVersion {
+                version: ECS_VERSION,
+            },
+            event: EcsEvent {
+                kind: if is_alert { "alert" } else { "event" },
+                category: vec!["file"],
+                event_type: vec!["info"],
+                module: "lnk_parser",
+                dataset: "lnk_parser.lnk",
+            },
+            file,
+            host,
+            process,
+            lnk,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::Value;
+
+    fn ecs_record(path: &str, analyze: bool) -> Value {
+        let parsed = LNKParser::from_path(path).unwrap();
+        let options = RecordOptions {
+            normalize: false,
+            analyze,
+            rules: None,
+        };
+        serde_json::to_value(EcsRecord::new(parsed, &options)).unwrap()
+    }
+
+    #[test]
+    fn test_file_fields() {
+        let record = ecs_record("samples/mal/exec_cmd_powershell.lnk", false);
+        let file = &record["file"];
+        assert!(file["path"]
+            .as_str()
+            .unwrap()
+            .ends_with("exec_cmd_powershell.lnk"));
+        assert_eq!(file["name"], "exec_cmd_powershell.lnk");
+        assert!(file["directory"].as_str().unwrap().ends_with("mal"));
+        assert_eq!(file["extension"], "lnk");
+        assert_eq!(
+            file["size"],
+            std::fs::metadata("samples/mal/exec_cmd_powershell.lnk")
+                .unwrap()
+                .len()
+        );
+        assert_eq!(file["target_path"], "C:\\Windows\\System32\\cmd.exe");
+        assert_eq!(file["hash"]["md5"], "e8b00981d6a02f2c1308d2dbedfa4840");
+        assert_eq!(
+            file["hash"]["sha1"],
+            "83b0b436a99774e041f9457f3ac5bc743f0653f7"
+        );
+        assert!(file["mtime"].is_string());
+        assert_eq!(record["@timestamp"], file["mtime"]);
+        // the normalized record of the file is under `lnk`
+        assert_eq!(record["lnk"]["target_size"], 331776);
+    }
+
+    #[test]
+    fn test_timestamp_without_metadata() {
+        let options = RecordOptions {
+            normalize: false,
+            analyze: false,
+            rules: None,
+        };
+        let mut data = std::fs::read("samples/mal/exec_cmd_powershell.lnk").unwrap();
+        let parsed = LNKParser::from_buffer(&data).unwrap();
+        let record = serde_json::to_value(EcsRecord::new(parsed, &options)).unwrap();
+        assert_eq!(
+            record["@timestamp"],
+            record["lnk"]["target_modification_time"]
+        );
+
+        // the header times are at offset 0x1C
+        data[0x1C..0x34].fill(0);
+        let parsed = LNKParser::from_buffer(&data).unwrap();
+        let record = serde_json::to_value(EcsRecord::new(parsed, &options)).unwrap();
+        assert_eq!(record["@timestamp"], "1970-01-01T00:00:00Z");
+    }
+
+    #[test]
+    fn test_host_mac() {
+        let record = ecs_record("samples/mal/exec_cmd_powershell.lnk", false);
+        assert_eq!(record["host"]["name"], "pc");
+        assert_eq!(
+            record["host"]["mac"],
+            serde_json::json!(["50-76-AF-A9-59-47"])
+        );
+
+        // no TrackerDataBlock, no host
+        let record = ecs_record("samples/WS19/10.0_1809/Süßspeisen.lnk", false);
+        assert!(record.get("host").is_none());
+    }
+
+    #[test]
+    fn test_process_command_line() {
+        let record = ecs_record("samples/mal/exec_cmd_powershell.lnk", false);
+        assert_eq!(
+            record["process"]["command_line"],
+            "C:\\Windows\\System32\\cmd.exe /c powershell.exe -c \"Write-Host This is a test\""
+        );
+        assert_eq!(
+            record["process"]["executable"],
+            "C:\\Windows\\System32\\cmd.exe"
+        );
+
+        // the targets with spaces are quoted
+        let record = ecs_record("samples/other/non_unicode_strings.lnk", false);
+        assert_eq!(
+            record["process"]["command_line"],
+            "\"E:\\Age Of Empires II\\Age2_X1\\age2_x1.Exe\" normalmouse"
+        );
+
+        // no arguments, no process
+        let record = ecs_record("samples/WS19/10.0_1809/Süßspeisen.lnk", false);
+        assert!(record.get("process").is_none());
+    }
+
+    #[test]
+    fn test_event_kind() {
+        let record = ecs_record("samples/mal/exec_cmd_powershell.lnk", true);
+        assert!(!record["lnk"]["findings"].as_array().unwrap().is_empty());
+        assert_eq!(record["event"]["kind"], "alert");
+
+        let record = ecs_record("samples/mal/exec_cmd_powershell.lnk", false);
+        assert_eq!(record["event"]["kind"], "event");
+        assert_eq!(record["event"]["category"], serde_json::json!(["file"]));
+    }
+}
@@ -0,0 +1,422 @@
+mod csv_format;
+mod dump;
+mod ecs;
+mod report;
+mod writers;
+
+use clap::{App, Arg, SubCommand};
+use csv_format::{CsvFormat, Encoding};
+use glob::glob;
+use lnk_parser::{diff::diff, rules::RuleSet, LNKParser};
+#[cfg(feature = "parquet")]
+use writers::ParquetOutput;
+#[cfg(feature = "sqlite")]
+use writers::SqliteOutput;
+use writers::{
+    BodyfileOutput, CsvOutput, EcsOutput, FingerprintGroupsOutput, HtmlOutput, IocsOutput,
+    JsonOutput, JsonlOutput, OutputFormat, OutputStream, OutputWriter, RecordOptions,
+    TimelineOutput, TlnOutput,
+};
+
+/// The names of the output formats, the sqlite and parquet formats are only available with their features.
+const OUTPUT_FORMATS: &[&str] = &[
+    "csv",
+    "jsonl",
+    "json",
+    "bodyfile",
+    "tln",
+    "timeline",
+    "ecs",
+    #[cfg(feature = "sqlite")]
+    "sqlite",
+    #[cfg(feature = "parquet")]
+    "parquet",
+    "html",
+];
+
+fn parse_cli_args() -> clap::ArgMatches<'static> {
+    App::new("lnk_parser")
+        .version(env!("CARGO_PKG_VERSION"))
+        .author("AbdulRhman Alfaifi - @A__ALFAIFI")
+        .about("Windows LNK Files Parser")
+        .arg(Arg::with_name("PATH")
+                .short("-p")
+                .long("--path")
+                .takes_value(true)
+                .multiple(true)
+                .value_name("PATH")
+                .help("Path(s) to LNK Metadata Files to be Parsed - accepts glob (Defaults to 'RecentItems' for all users)"))
+        .arg(
+            Arg::with_name("output")
+                .short("-o")
+                .long("--output")
+                .default_value("stdout")
+                .takes_value(true)
+                .help("The file path to write the output to"))
+        .arg(
+            Arg::with_name("output-format")
+            .long("--output-format")
+            .takes_value(true)
+            .possible_values(OUTPUT_FORMATS)
+            .default_value("csv")
+            .help("Output format. 'bodyfile' writes Sleuth Kit bodyfile lines of the LNK file and its target (input for mactime), \
+                'tln' writes a TLN line per timestamp, 'timeline' writes a CSV row per timestamp sorted by time \
+                'ecs' writes Elastic Common Schema documents as NDJSON for the Elasticsearch bulk API \
+                'sqlite' writes the parsed files to a SQLite database (requires '--output' and the 'sqlite' feature) \
+                'parquet' writes the normalized records to a Parquet file (requires the 'parquet' feature) \
+                and 'html' writes a self-contained HTML report with the findings and a timeline chart"))
+        .arg(
+            Arg::with_name("no-headers")
+                .long("--no-headers")
+                .takes_value(false)
+                .help("Don't print headers when using CSV as the output format"))
+        .arg(
+            Arg::with_name("fields")
+                .long("--fields")
+                .takes_value(true)
+                .value_name("FIELDS")
+                .use_delimiter(true)
+                .help("Comma separated list of the columns to write with the csv output format, in the given order (e.g. 'lnk_full_path,target_full_path,command_line_arguments')"))
+        .arg(
+            Arg::with_name("flatten")
+                .long("--flatten")
+                .takes_value(false)
+                .help("Write the parsed data (raw unless '--normalize') with the csv output format using flattened dotted field names (e.g. 'shell_link_header.file_size'). \
+                    The columns are in the order they first appear. Without '--fields' the rows are kept in memory and written after all the files are parsed"))
+        .arg(
+            Arg::with_name("delimiter")
+                .long("--delimiter")
+                .takes_value(true)
+                .possible_values(&["comma", "tab"])
+                .default_value("comma")
+                .help("The delimiter of the CSV output"))
+        .arg(
+            Arg::with_name("line-ending")
+                .long("--line-ending")
+                .takes_value(true)
+                .possible_values(&["crlf", "lf"])
+                .default_value("crlf")
+                .help("The line ending of the CSV output"))
+        .arg(
+            Arg::with_name("encoding")
+                .long("--encoding")
+                .takes_value(true)
+                .possible_values(&["utf-8", "utf-8-bom", "utf-16le"])
+                .default_value("utf-8")
+                .help("The text encoding of the CSV output ('utf-8-bom' and 'utf-16le' start with a byte order mark)"))
+        .arg(
+            Arg::with_name("normalize")
+                .long("--normalize")
+                .takes_value(false)
+                .help("Normalize the result to the most important fields"))
+        .arg(
+            Arg::with_name("no-validation")
+                .long("--no-validation")
+                .takes_value(false)
+                .help("Parse files even if the ShellLinkHeader size and CLSID are invalid (useful for carved files)"))
+        .arg(
+            Arg::with_name("group-by-fingerprint")
+                .long("--group-by-fingerprint")
+                .takes_value(false)
+                .help("Group the parsed files by their structural fingerprint instead of writing a record per file"))
+        .arg(
+            Arg::with_name("analyze")
+                .long("--analyze")
+                .takes_value(false)
+                .help("Run the built-in heuristics and add the findings to the output"))
+        .arg(
+            Arg::with_name("rules")
+                .long("--rules")
+                .takes_value(true)
+                .value_name("RULES_FILE")
+                .help("YAML or TOML file with detection rules, the IDs of the matching rules are added to the output"))
+        .arg(
+            Arg::with_name("iocs")
+                .long("--iocs")
+                .takes_value(false)
+                .conflicts_with("group-by-fingerprint")
+                .help("Only output the IOCs extracted from the parsed files (the JSON output format is a STIX 2.1 bundle)"))
+        .subcommand(
+            SubCommand::with_name("dump")
+                .about("Print every structure of a LNK file with its offset, size, raw bytes and decoded value")
+                .arg(
+                    Arg::with_name("FILE")
+                        .required(true)
+                        .index(1)
+                        .help("The LNK file to dump"))
+                .arg(
+                    Arg::with_name("json")
+                        .long("--json")
+                        .takes_value(false)
+                        .help("Write the structures as JSON with the hex encoded bytes of each field"))
+                .arg(
+                    Arg::with_name("no-validation")
+                        .long("--no-validation")
+                        .takes_value(false)
+                        .help("Parse the file even if the ShellLinkHeader size and CLSID are invalid")))
+        .subcommand(
+            SubCommand::with_name("diff")
+                .about("Compare two LNK files structurally and print the added (+), removed (-) and changed (~) fields. \
+                    Exits with 0 if the files are the same, 1 if they differ and 2 on errors")
+                .arg(
+                    Arg::with_name("OLD")
+                        .required(true)
+                        .index(1)
+                        .help("The LNK file to compare against"))
+                .arg(
+                    Arg::with_name("NEW")
+                        .required(true)
+                        .index(2)
+                        .help("The LNK file to compare"))
+                .arg(
+                    Arg::with_name("json")
+                        .long("--json")
+                        .takes_value(false)
+                        .help("Write the differences as JSON"))
+                .arg(
+                    Arg::with_name("no-validation")
+                        .long("--no-validation")
+                        .takes_value(false)
+                        .help("Parse the files even if the ShellLinkHeader size and CLSID are invalid")))
+        .get_matches()
+}
+
+/// Print the differences between two LNK files, returns the exit code.
+fn diff_files(args: &clap::ArgMatches) -> i32 {
+    let validate = args.occurrences_of("no-validation") == 0;
+    let mut parsed = vec![];
+    for path in [args.value_of("OLD").unwrap(), args.value_of("NEW").unwrap()].iter() {
+        let result = if validate {
+            LNKParser::from_path(path)
+        } else {
+            LNKParser::from_path_unchecked(path)
+        };
+        match result {
+            Ok(lnk) => parsed.push(lnk),
+            Err(e) => {
+                eprintln!("Did not parse '{}' correctly. ERROR : '{}'", path, e);
+                return 2;
+            }
+        }
+    }
+    let differences = diff(&parsed[0], &parsed[1]);
+    if args.occurrences_of("json") > 0 {
+        match serde_json::to_string_pretty(&differences) {
+            Ok(json_data) => println!("{}", json_data),
+            Err(e) => {
+                eprintln!("Error Writing Data ! ERROR : '{}'", e);
+                return 2;
+            }
+        }
+    } else {
+        for difference in &differences {
+            println!("{}", difference);
+        }
+    }
+    if differences.is_empty() {
+        0
+    } else {
+        1
+    }
+}
+
+/// The options of the CSV outputs.
+struct CsvOptions {
+    format: CsvFormat,
+    fields: Option<Vec<String>>,
+    flatten: bool,
+}
+
+/// Create the writer of the output format.
+fn create_writer(
+    output_format: OutputFormat,
+    output_to: &str,
+    options: RecordOptions,
+    csv_options: CsvOptions,
+    group_by_fingerprint: bool,
+    iocs_only: bool,
+    headers: bool,
+) -> std::io::Result<Box<dyn OutputWriter>> {
+    #[cfg(feature = "sqlite")]
+    if let OutputFormat::SQLITE = output_format {
+        return Ok(Box::new(SqliteOutput::new(output_to, options)?));
+    }
+    let csv_format = csv_options.format;
+    // only the CSV outputs use the configured encoding
+    let encoding = match output_format {
+        OutputFormat::CSV | OutputFormat::TIMELINE => csv_format.encoding,
+        _ => Encoding::Utf8,
+    };
+    let output = OutputStream::open(output_to, encoding)?;
+    let writer: Box<dyn OutputWriter> = if group_by_fingerprint {
+        Box::new(FingerprintGroupsOutput::new(
+            output,
+            output_format,
+            csv_format,
+            headers,
+        )?)
+    } else if iocs_only {
+        Box::new(IocsOutput::new(output, output_format, csv_format, headers)?)
+    } else {
+        match output_format {
+            OutputFormat::JSON => Box::new(JsonOutput::new(output, options)),
+            OutputFormat::JSONL => Box::new(JsonlOutput::new(output, options)),
+            OutputFormat::CSV => Box::new(CsvOutput::new(
+                output,
+                options,
+                csv_format,
+                csv_options.fields,
+                csv_options.flatten,
+                headers,
+            )?),
+            OutputFormat::BODYFILE => Box::new(BodyfileOutput::new(output)),
+            OutputFormat::TLN => Box::new(TlnOutput::new(output)),
+            OutputFormat::TIMELINE => Box::new(TimelineOutput::new(output, csv_format, headers)?),
+            OutputFormat::ECS => Box::new(EcsOutput::new(output, options)),
+            #[cfg(feature = "parquet")]
+            OutputFormat::PARQUET => Box::new(ParquetOutput::new(output, options)?),
+            OutputFormat::HTML => Box::new(HtmlOutput::new(output, options)),
+            #[cfg(feature = "sqlite")]
+            OutputFormat::SQLITE => unreachable!(),
+        }
+    };
+    Ok(writer)
+}
+
+fn main() {
+    let args = parse_cli_args();
+    if let Some(dump_args) = args.subcommand_matches("dump") {
+        let path = dump_args.value_of("FILE").unwrap();
+        let validate = dump_args.occurrences_of("no-validation") == 0;
+        let json = dump_args.occurrences_of("json") > 0;
+        if let Err(e) = dump::dump(&mut std::io::stdout(), path, validate, json) {
+            eprintln!("Can not dump '{}'. ERROR : '{}'", path, e);
+            std::process::exit(1);
+        }
+        return;
+    }
+    if let Some(diff_args) = args.subcommand_matches("diff") {
+        std::process::exit(diff_files(diff_args));
+    }
+    let output_format = OutputFormat::from_str(args.value_of("output-format").unwrap());
+    let output_to = args.value_of("output").unwrap();
+    let normalize = !matches!(args.occurrences_of("normalize"), 0);
+    let validate = matches!(args.occurrences_of("no-validation"), 0);
+    let group_by_fingerprint = !matches!(args.occurrences_of("group-by-fingerprint"), 0);
+    let analyze = !matches!(args.occurrences_of("analyze"), 0);
+    let iocs_only = !matches!(args.occurrences_of("iocs"), 0);
+    let headers = args.occurrences_of("no-headers") == 0;
+    let csv_options = CsvOptions {
+        format: CsvFormat {
+            delimiter: match args.value_of("delimiter") {
+                Some("tab") => b'\t',
+                _ => b',',
+            },
+            crlf: args.value_of("line-ending") != Some("lf"),
+            encoding: Encoding::from_str(args.value_of("encoding").unwrap()),
+        },
+        fields: args
+            .values_of("fields")
+            .map(|fields| fields.map(|field| field.trim().to_string()).collect()),
+        flatten: !matches!(args.occurrences_of("flatten"), 0),
+    };
+    let rules = args
+        .value_of("rules")
+        .map(|path| match RuleSet::from_path(path) {
+            Ok(rules) => rules,
+            Err(e) => {
+                eprintln!("Can not load the rules from '{}'. ERROR : '{}'", path, e);
+                std::process::exit(1);
+            }
+        });
+    let per_file_only = match output_format {
+        OutputFormat::BODYFILE
+        | OutputFormat::TLN
+        | OutputFormat::TIMELINE
+        | OutputFormat::ECS
+        | OutputFormat::HTML => true,
+        #[cfg(feature = "sqlite")]
+        OutputFormat::SQLITE => true,
+        #[cfg(feature = "parquet")]
+        OutputFormat::PARQUET => true,
+        OutputFormat::JSON | OutputFormat::JSONL | OutputFormat::CSV => false,
+    };
+    if per_file_only && (group_by_fingerprint || iocs_only) {
+        eprintln!("The bodyfile, tln, timeline, ecs, sqlite, parquet and html output formats can not be used with '--group-by-fingerprint' or '--iocs'");
+        std::process::exit(1);
+    }
+    let record_csv =
+        matches!(output_format, OutputFormat::CSV) && !group_by_fingerprint && !iocs_only;
+    if (csv_options.fields.is_some() || csv_options.flatten) && !record_csv {
+        eprintln!("'--fields' and '--flatten' can only be used with the csv output format without '--group-by-fingerprint' or '--iocs'");
+        std::process::exit(1);
+    }
+    #[cfg(feature = "sqlite")]
+    if let OutputFormat::SQLITE = output_format {
+        if output_to == "stdout" {
+            eprintln!("The sqlite output format requires the database path in '--output'");
+            std::process::exit(1);
+        }
+    }
+    let options = RecordOptions {
+        normalize,
+        analyze,
+        rules,
+    };
+    let mut writer = match create_writer(
+        output_format,
+        output_to,
+        options,
+        csv_options,
+        group_by_fingerprint,
+        iocs_only,
+        headers,
+    ) {
+        Ok(writer) => writer,
+        Err(e) => {
+            eprintln!("Can not create the output '{}'. ERROR : '{}'", output_to, e);
+            std::process::exit(1);
+        }
+    };
+
+    let mut lnk_file_paths = vec![
+        "C:\\Windows\\SysWOW64\\config\\systemprofile\\AppData\\Roaming\\Microsoft\\Windows\\**\\*.lnk",
+        "C:\\Windows\\System32\\config\\systemprofile\\AppData\\Roaming\\Microsoft\\Windows\\**\\*.lnk",
+        "C:\\Users\\*\\AppData\\Roaming\\Microsoft\\Windows\\Recent\\*.lnk"
+    ];
+    if args.occurrences_of("PATH") > 0 {
+        // override lnk_file_paths if the argument -p/--path is specified.
+        lnk_file_paths = args.values_of("PATH").unwrap().collect();
+    }
+
+    for dir in lnk_file_paths {
+        for entry in glob(dir).expect("Failed to read glob pattern") {
+            match entry {
+                Ok(path) => {
+                    let full_path = path.as_path().to_str().unwrap();
+                    let parsed = if validate {
+                        LNKParser::from_path(full_path)
+                    } else {
+                        LNKParser::from_path_unchecked(full_path)
+                    };
+                    match parsed {
+                        Ok(parsed) => {
+                            if let Err(e) = writer.write(full_path, parsed) {
+                                eprintln!("Error Writing Data ! ERROR : '{}'", e);
+                                std::process::exit(1);
+                            }
+                        }
+                        Err(e) => {
+                            eprintln!("Did not parse '{}' correctly. ERROR : '{}'", full_path, e);
+                        }
+                    };
+                }
+                Err(e) => eprintln!("{:?}", e),
+            }
+        }
+    }
+    if let Err(e) = writer.finish() {
+        eprintln!("Error Writing Data ! ERROR : '{}'", e);
+        std::process::exit(1);
+    }
+}
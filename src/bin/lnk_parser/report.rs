@@ -0,0 +1,507 @@
+//! A self-contained HTML report of the parsed files (no external resources, the styles and the
+//! table sorting/filtering script are inline).
+//!
+//! The report has a table of the parsed files, a chart of the timestamps and the details of every
+//! structure of each file, the files with heuristic findings are highlighted.
+
+use chrono::{DateTime, Utc};
+use lnk_parser::{analysis::Finding, rules::RuleMatch, timeline::TimelineEvent, LNKParser};
+use serde_json::Value;
+use std::collections::HashMap;
+use std::fmt::Write;
+
+/// The columns of the files table, the normalized record field of each column.
+const COLUMNS: [(&str, &str); 7] = [
+    ("LNK file", "lnk_full_path"),
+    ("Target", "target_full_path"),
+    ("Arguments", "command_line_arguments"),
+    ("Target created", "target_creation_time"),
+    ("Target modified", "target_modification_time"),
+    ("Target accessed", "target_access_time"),
+    ("Host", "target_hostname"),
+];
+
+/// The structures shown in the file details, the fields of the parsed file in each structure.
+const STRUCTURES: [(&str, &[&str]); 7] = [
+    ("LNK file", &["lnk_file_metadata", "hashes"]),
+    ("ShellLinkHeader", &["shell_link_header"]),
+    ("LinkTargetIDList", &["link_target_id_list"]),
+    ("LinkInfo", &["link_info"]),
+    (
+        "StringData",
+        &[
+            "name_string",
+            "relative_path",
+            "working_dir",
+            "command_line_arguments",
+            "icon_location",
+        ],
+    ),
+    ("Decoded arguments", &["decoded_arguments"]),
+    ("ExtraData", &["extra_data"]),
+];
+
+/// The size of the timeline chart plot area.
+const CHART_WIDTH: f64 = 960.0;
+const CHART_LANE_HEIGHT: f64 = 28.0;
+const CHART_MARGIN: f64 = 140.0;
+
+const STYLE: &str = r#"
+body { font-family: -apple-system, "Segoe UI", Helvetica, Arial, sans-serif; font-size: 14px; margin: 24px; color: #222; }
+h1 { font-size: 22px; } h2 { font-size: 18px; margin-top: 32px; } h3 { font-size: 15px; margin: 16px 0 6px; }
+table { border-collapse: collapse; }
+#files th, #files td, .events th, .events td { border: 1px solid #ccc; padding: 4px 8px; text-align: left; vertical-align: top; }
+#files th { background: #f0f0f0; cursor: pointer; user-select: none; }
+#files td { max-width: 420px; word-break: break-all; }
+tr.sev-High { background: #fbd5d5; } tr.sev-Medium { background: #fde8c8; } tr.sev-Low { background: #fdf6c8; } tr.sev-Info { background: #e4effa; }
+.badge { display: inline-block; padding: 1px 6px; border-radius: 3px; font-size: 12px; color: #fff; background: #888; }
+.badge.High { background: #c81e1e; } .badge.Medium { background: #d97706; } .badge.Low { background: #b59f00; } .badge.Info { background: #3b7dd8; }
+.kv { margin: 2px 0; } .kv th { text-align: left; font-weight: normal; color: #555; padding: 1px 12px 1px 0; vertical-align: top; white-space: nowrap; }
+.kv td { padding: 1px 0; word-break: break-all; } .null { color: #aaa; }
+details.file { border: 1px solid #ccc; border-radius: 4px; margin: 8px 0; padding: 6px 10px; }
+details.file > summary { cursor: pointer; font-weight: bold; }
+.findings li { margin: 4px 0; }
+.controls { margin: 12px 0; } .controls input { width: 320px; padding: 4px; }
+svg text { font-size: 11px; fill: #444; }
+"#;
+
+const SCRIPT: &str = r#"
+function sortTable(th) {
+  var table = th.closest("table"), body = table.tBodies[0];
+  var index = Array.prototype.indexOf.call(th.parentNode.children, th);
+  var ascending = th.dataset.order !== "asc";
+  Array.prototype.forEach.call(th.parentNode.children, function (h) { delete h.dataset.order; });
+  th.dataset.order = ascending ? "asc" : "desc";
+  var key = function (row) { var cell = row.children[index]; return cell.dataset.sort !== undefined ? cell.dataset.sort : cell.textContent; };
+  var rows = Array.prototype.slice.call(body.rows);
+  rows.sort(function (a, b) {
+    var x = key(a), y = key(b), nx = parseFloat(x), ny = parseFloat(y);
+    var c = (!isNaN(nx) && !isNaN(ny)) ? nx - ny : x.localeCompare(y);
+    return ascending ? c : -c;
+  });
+  rows.forEach(function (row) { body.appendChild(row); });
+}
+function filterTable() {
+  var text = document.getElementById("filter").value.toLowerCase();
+  var severity = parseInt(document.getElementById("severity").value, 10);
+  Array.prototype.forEach.call(document.getElementById("files").tBodies[0].rows, function (row) {
+    var visible = row.textContent.toLowerCase().indexOf(text) !== -1 && parseInt(row.dataset.severity, 10) >= severity;
+    row.style.display = visible ? "" : "none";
+  });
+}
+"#;
+
+/// A parsed file of the report.
+struct ReportFile {
+    fields: HashMap<&'static str, String>,
+    findings: Vec<Finding>,
+    rule_matches: Vec<RuleMatch>,
+    data: Value,
+}
+
+/// Collects the parsed files and renders the report.
+#[derive(Default)]
+pub struct HtmlReport {
+    files: Vec<ReportFile>,
+    /// The timeline events and the index of the file they came from.
+    events: Vec<(usize, TimelineEvent)>,
+}
+
+impl HtmlReport {
+    /// Add a parsed file, the heuristic findings are always part of the report.
+    pub fn add(
+        &mut self,
+        parsed: LNKParser,
+        rule_matches: Vec<RuleMatch>,
+    ) -> serde_json::Result<()> {
+        let index = self.files.len();
+        let mut data = serde_json::to_value(&parsed)?;
+        if let Some(decoded) = parsed.deobfuscated_arguments() {
+            data["decoded_arguments"] = serde_json::to_value(decoded)?;
+        }
+        self.events.extend(
+            parsed
+                .timeline_events()
+                .into_iter()
+                .map(|event| (index, event)),
+        );
+        self.files.push(ReportFile {
+            fields: parsed.to_normalized_record().fields().into_iter().collect(),
+            findings: parsed.analyze(),
+            rule_matches,
+            data,
+        });
+        Ok(())
+    }
+
+    /// The HTML document, `created` is the generation time shown in the report.
+    pub fn render(&mut self, created: DateTime<Utc>) -> String {
+        self.events.sort_by_key(|(_, event)| event.timestamp);
+        let mut html = String::new();
+        html.push_str("<!DOCTYPE html>\n<html lang=\"en\">\n<head>\n<meta charset=\"utf-8\">\n");
+        html.push_str("<title>LNK Parser Report</title>\n");
+        let _ = writeln!(html, "<style>{}</style>", STYLE);
+        let _ = writeln!(html, "<script>{}</script>", SCRIPT);
+        html.push_str("</head>\n<body>\n<h1>LNK Parser Report</h1>\n");
+        let _ = writeln!(
+            html,
+            "<p>Generated by lnk_parser {} at {}. {} file(s), {} with findings.</p>",
+            env!("CARGO_PKG_VERSION"),
+            created.format("%Y-%m-%d %H:%M:%S UTC"),
+            self.files.len(),
+            self.files.iter().filter(|f| !f.findings.is_empty()).count()
+        );
+        self.render_table(&mut html);
+        self.render_timeline(&mut html);
+        html.push_str("<h2>Files</h2>\n");
+        for (index, file) in self.files.iter().enumerate() {
+            render_file(&mut html, index, file);
+        }
+        html.push_str("</body>\n</html>\n");
+        html
+    }
+
+    fn render_table(&self, html: &mut String) {
+        html.push_str("<h2>Shortcuts</h2>\n<div class=\"controls\">\n");
+        html.push_str("<input id=\"filter\" type=\"search\" placeholder=\"Filter\" oninput=\"filterTable()\">\n");
+        html.push_str("<select id=\"severity\" onchange=\"filterTable()\">");
+        html.push_str(
+            "<option value=\"-1\">All files</option><option value=\"0\">With findings</option>",
+        );
+        html.push_str("<option value=\"2\">Medium or higher</option><option value=\"3\">High</option></select>\n");
+        html.push_str(
+            "</div>\n<table id=\"files\">\n<thead><tr><th onclick=\"sortTable(this)\">#</th>",
+        );
+        html.push_str("<th onclick=\"sortTable(this)\">Severity</th>");
+        for (title, _) in COLUMNS.iter() {
+            let _ = write!(html, "<th onclick=\"sortTable(this)\">{}</th>", title);
+        }
+        html.push_str("<th onclick=\"sortTable(this)\">Findings</th></tr></thead>\n<tbody>\n");
+        for (index, file) in self.files.iter().enumerate() {
+            let severity = file.findings.first().map(|finding| finding.severity);
+            let rank = severity.map(|severity| severity as i32).unwrap_or(-1);
+            let _ = write!(
+                html,
+                "<tr data-severity=\"{}\"{}><td data-sort=\"{}\"><a href=\"#file-{}\">{}</a></td><td data-sort=\"{}\">{}</td>",
+                rank,
+                severity
+                    .map(|severity| format!(" class=\"sev-{}\"", severity))
+                    .unwrap_or_default(),
+                index + 1,
+                index + 1,
+                index + 1,
+                rank,
+                severity.map(badge).unwrap_or_default()
+            );
+            for (_, field) in COLUMNS.iter() {
+                let value = file
+                    .fields
+                    .get(field)
+                    .map(String::as_str)
+                    .unwrap_or_default();
+                let _ = write!(html, "<td>{}</td>", escape(value));
+            }
+            let ids = file
+                .findings
+                .iter()
+                .map(|finding| finding.id.to_string())
+                .chain(
+                    file.rule_matches
+                        .iter()
+                        .map(|rule_match| rule_match.id.to_owned()),
+                )
+                .collect::<Vec<String>>();
+            let _ = writeln!(html, "<td>{}</td></tr>", escape(&ids.join(", ")));
+        }
+        html.push_str("</tbody>\n</table>\n");
+    }
+
+    /// A chart with a lane per timestamp source and a point per timestamp.
+    fn render_timeline(&self, html: &mut String) {
+        html.push_str("<h2>Timeline</h2>\n");
+        let (first, last) = match (self.events.first(), self.events.last()) {
+            (Some((_, first)), Some((_, last))) => (first.timestamp, last.timestamp),
+            _ => {
+                html.push_str("<p>No timestamps.</p>\n");
+                return;
+            }
+        };
+        let mut lanes: Vec<&str> = vec![];
+        for (_, event) in &self.events {
+            if !lanes.contains(&event.source.as_str()) {
+                lanes.push(&event.source);
+            }
+        }
+        let span = (last - first).num_seconds().max(1) as f64;
+        let x = |timestamp: DateTime<Utc>| {
+            CHART_MARGIN + (timestamp - first).num_seconds() as f64 / span * CHART_WIDTH
+        };
+        let axis_y = lanes.len() as f64 * CHART_LANE_HEIGHT + 10.0;
+        let _ = writeln!(
+            html,
+            "<svg width=\"{}\" height=\"{}\" xmlns=\"http://www.w3.org/2000/svg\">",
+            CHART_MARGIN + CHART_WIDTH + 40.0,
+            axis_y + 30.0
+        );
+        for (i, lane) in lanes.iter().enumerate() {
+            let y = i as f64 * CHART_LANE_HEIGHT + CHART_LANE_HEIGHT / 2.0;
+            let _ = writeln!(
+                html,
+                "<text x=\"0\" y=\"{:.1}\">{}</text><line x1=\"{}\" y1=\"{:.1}\" x2=\"{}\" y2=\"{:.1}\" stroke=\"#eee\"/>",
+                y + 4.0,
+                escape(lane),
+                CHART_MARGIN,
+                y,
+                CHART_MARGIN + CHART_WIDTH,
+                y
+            );
+        }
+        let _ = writeln!(
+            html,
+            "<line x1=\"{}\" y1=\"{:.1}\" x2=\"{}\" y2=\"{:.1}\" stroke=\"#888\"/>",
+            CHART_MARGIN,
+            axis_y,
+            CHART_MARGIN + CHART_WIDTH,
+            axis_y
+        );
+        for tick in 0..=4 {
+            let timestamp = first + chrono::Duration::seconds((span * tick as f64 / 4.0) as i64);
+            let _ = writeln!(
+                html,
+                "<text x=\"{:.1}\" y=\"{:.1}\" text-anchor=\"middle\">{}</text>",
+                x(timestamp),
+                axis_y + 18.0,
+                timestamp.format("%Y-%m-%d")
+            );
+        }
+        for (index, event) in &self.events {
+            let lane = lanes
+                .iter()
+                .position(|lane| *lane == event.source)
+                .unwrap_or_default();
+            let severity = self.files[*index].findings.first().map(|f| f.severity);
+            let _ = writeln!(
+                html,
+                "<a href=\"#file-{}\"><circle cx=\"{:.1}\" cy=\"{:.1}\" r=\"4\" fill=\"{}\" fill-opacity=\"0.6\"><title>{} {} {} ({})</title></circle></a>",
+                index + 1,
+                x(event.timestamp),
+                lane as f64 * CHART_LANE_HEIGHT + CHART_LANE_HEIGHT / 2.0,
+                if severity.is_some() { "#c81e1e" } else { "#3b7dd8" },
+                event.timestamp.format("%Y-%m-%d %H:%M:%S"),
+                escape(&event.source),
+                escape(&event.description),
+                escape(event.lnk_full_path.as_deref().unwrap_or_default())
+            );
+        }
+        html.push_str("</svg>\n");
+        let _ = writeln!(
+            html,
+            "<details><summary>Timeline events ({})</summary>\n<table class=\"events\">",
+            self.events.len()
+        );
+        html.push_str("<tr><th>Timestamp</th><th>Source</th><th>Description</th><th>LNK file</th><th>Target</th><th>Host</th></tr>\n");
+        for (index, event) in &self.events {
+            let _ = writeln!(
+                html,
+                "<tr><td>{}</td><td>{}</td><td>{}</td><td><a href=\"#file-{}\">{}</a></td><td>{}</td><td>{}</td></tr>",
+                event.timestamp.format("%Y-%m-%dT%H:%M:%SZ"),
+                escape(&event.source),
+                escape(&event.description),
+                index + 1,
+                escape(event.lnk_full_path.as_deref().unwrap_or_default()),
+                escape(event.target_full_path.as_deref().unwrap_or_default()),
+                escape(event.host.as_deref().unwrap_or_default())
+            );
+        }
+        html.push_str("</table>\n</details>\n");
+    }
+}
+
+/// The details of every structure of a parsed file.
+fn render_file(html: &mut String, index: usize, file: &ReportFile) {
+    let lnk_full_path = file.fields.get("lnk_full_path").map(String::as_str);
+    let target_full_path = file.fields.get("target_full_path").map(String::as_str);
+    let _ = writeln!(
+        html,
+        "<details class=\"file\" id=\"file-{}\"{}><summary>{}. {} &rarr; {} {}</summary>",
+        index + 1,
+        if file.findings.is_empty() {
+            ""
+        } else {
+            " open"
+        },
+        index + 1,
+        escape(lnk_full_path.unwrap_or_default()),
+        escape(target_full_path.unwrap_or_default()),
+        file.findings
+            .first()
+            .map(|finding| badge(finding.severity))
+            .unwrap_or_default()
+    );
+    if !file.findings.is_empty() || !file.rule_matches.is_empty() {
+        html.push_str("<h3>Findings</h3>\n<ul class=\"findings\">\n");
+        for finding in &file.findings {
+            let _ = writeln!(
+                html,
+                "<li>{} <b>{}</b> {}</li>",
+                badge(finding.severity),
+                finding.id,
+                escape(&finding.rationale)
+            );
+        }
+        for rule_match in &file.rule_matches {
+            let _ = writeln!(
+                html,
+                "<li><span class=\"badge\">Rule</span> <b>{}</b> {}</li>",
+                escape(&rule_match.id),
+                escape(rule_match.description.as_deref().unwrap_or_default())
+            );
+        }
+        html.push_str("</ul>\n");
+    }
+    for (title, fields) in STRUCTURES.iter() {
+        let values = fields
+            .iter()
+            .filter_map(|field| file.data.get(field).map(|value| (*field, value)))
+            .collect::<Vec<(&str, &Value)>>();
+        if values.is_empty() {
+            continue;
+        }
+        let _ = writeln!(html, "<h3>{}</h3>", title);
+        if let [(_, value)] = values.as_slice() {
+            html.push_str(&value_html(value));
+        } else {
+            html.push_str("<table class=\"kv\">");
+            for (field, value) in values {
+                let _ = write!(
+                    html,
+                    "<tr><th>{}</th><td>{}</td></tr>",
+                    field,
+                    value_html(value)
+                );
+            }
+            html.push_str("</table>");
+        }
+        html.push('\n');
+    }
+    html.push_str("</details>\n");
+}
+
+/// A JSON value as nested key/value tables.
+fn value_html(value: &Value) -> String {
+    match value {
+        Value::Null => "<span class=\"null\">-</span>".to_string(),
+        Value::String(s) => escape(s),
+        Value::Object(map) => {
+            let mut html = String::from("<table class=\"kv\">");
+            for (key, value) in map {
+                let _ = write!(
+                    html,
+                    "<tr><th>{}</th><td>{}</td></tr>",
+                    escape(key),
+                    value_html(value)
+                );
+            }
+            html.push_str("</table>");
+            html
+        }
+        Value::Array(values) if values.iter().any(|v| v.is_object() || v.is_array()) => {
+            let mut html = String::from("<ol start=\"0\">");
+            for value in values {
+                let _ = write!(html, "<li>{}</li>", value_html(value));
+            }
+            html.push_str("</ol>");
+            html
+        }
+        Value::Array(values) => values
+            .iter()
+            .map(value_html)
+            .collect::<Vec<String>>()
+            .join(", "),
+        _ => value.to_string(),
+    }
+}
+
+fn badge(severity: lnk_parser::analysis::Severity) -> String {
+    format!("<span class=\"badge {0}\">{0}</span>", severity)
+}
+
+/// Escape the HTML special characters.
+fn escape(s: &str) -> String {
+    let mut escaped = String::with_capacity(s.len());
+    for c in s.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&#39;"),
+            _ => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// The sample with the arguments replaced by a value that contains HTML.
+    fn crafted_sample() -> LNKParser {
+        let mut data = std::fs::read("samples/mal/exec_cmd_powershell.lnk").unwrap();
+        let utf16 = |s: &str| {
+            s.encode_utf16()
+                .flat_map(u16::to_le_bytes)
+                .collect::<Vec<u8>>()
+        };
+        let original = utf16("Write-Host This is a test");
+        let injected = utf16("<script>abc('\"')</script>");
+        assert_eq!(original.len(), injected.len());
+        let position = data
+            .windows(original.len())
+            .position(|w| w == original.as_slice())
+            .unwrap();
+        data[position..position + injected.len()].copy_from_slice(&injected);
+        LNKParser::from_buffer(&data).unwrap()
+    }
+
+    #[test]
+    fn test_render() {
+        let mut report = HtmlReport::default();
+        for path in glob::glob("samples/mal/*.lnk").unwrap() {
+            let path = path.unwrap();
+            let parsed = LNKParser::from_path(path.to_str().unwrap()).unwrap();
+            report.add(parsed, vec![]).unwrap();
+        }
+        report.add(crafted_sample(), vec![]).unwrap();
+        let html = report.render(Utc::now());
+
+        // the only script is the one of the report
+        assert_eq!(html.matches("<script").count(), 1);
+        assert!(!html.contains("<script>abc"));
+        assert!(html.contains("&lt;script&gt;abc(&#39;&quot;&#39;)&lt;/script&gt;"));
+
+        assert!(html.contains("3 file(s), 3 with findings."));
+        assert!(html.contains("class=\"sev-Medium\""));
+        assert!(html.contains("<span class=\"badge Medium\">Medium</span>"));
+        assert!(html.contains("lolbin_in_arguments"));
+        assert!(html.contains("<svg"));
+        assert!(!html.contains("No timestamps."));
+    }
+
+    #[test]
+    fn test_render_without_timestamps() {
+        let html = HtmlReport::default().render(Utc::now());
+        assert!(html.contains("0 file(s), 0 with findings."));
+        assert!(html.contains("<p>No timestamps.</p>"));
+        assert!(!html.contains("<svg"));
+    }
+
+    #[test]
+    fn test_escape() {
+        assert_eq!(
+            escape("<a href=\"x\">'&'</a>"),
+            "&lt;a href=&quot;x&quot;&gt;&#39;&amp;&#39;&lt;/a&gt;"
+        );
+    }
+}
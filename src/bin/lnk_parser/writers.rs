@@ -0,0 +1,993 @@
+//! The output writers, every output format implements `OutputWriter`.
+//!
+//! The writers stream the records to the output as the files are parsed, only the formats
+//! that need all the records before writing (e.g. the sorted timeline) keep them in memory.
+
+use crate::csv_format::{flatten, CsvFormat, Encoding};
+use crate::ecs::EcsRecord;
+use crate::report::HtmlReport;
+use chrono::Utc;
+#[cfg(feature = "parquet")]
+use lnk_parser::parquet::ParquetWriter;
+#[cfg(feature = "sqlite")]
+use lnk_parser::sqlite::SqliteWriter;
+use lnk_parser::{
+    analysis::Finding,
+    deobfuscation::DecodedArguments,
+    fingerprint::FingerprintFeatures,
+    iocs::{to_stix_bundle, TaggedIoc},
+    rules::{RuleMatch, RuleSet},
+    timeline::TimelineEvent,
+    LNKParser, NormalizedRecord,
+};
+use serde::Serialize;
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::{self, BufWriter, Write};
+
+/// The output is flushed after writing this number of records.
+const FLUSH_INTERVAL: usize = 100;
+
+#[allow(clippy::upper_case_acronyms)]
+pub enum OutputFormat {
+    JSON,
+    JSONL,
+    CSV,
+    BODYFILE,
+    TLN,
+    TIMELINE,
+    ECS,
+    #[cfg(feature = "sqlite")]
+    SQLITE,
+    #[cfg(feature = "parquet")]
+    PARQUET,
+    HTML,
+}
+
+impl OutputFormat {
+    pub fn from_str(s: &str) -> OutputFormat {
+        match s {
+            "json" => OutputFormat::JSON,
+            "jsonl" => OutputFormat::JSONL,
+            "csv" => OutputFormat::CSV,
+            "bodyfile" => OutputFormat::BODYFILE,
+            "tln" => OutputFormat::TLN,
+            "timeline" => OutputFormat::TIMELINE,
+            "ecs" => OutputFormat::ECS,
+            #[cfg(feature = "sqlite")]
+            "sqlite" => OutputFormat::SQLITE,
+            #[cfg(feature = "parquet")]
+            "parquet" => OutputFormat::PARQUET,
+            "html" => OutputFormat::HTML,
+            _ => OutputFormat::CSV,
+        }
+    }
+}
+
+/// Writes the parsed files in an output format.
+pub trait OutputWriter {
+    /// Write a parsed file, `path` is the path the file was parsed from.
+    fn write(&mut self, path: &str, parsed: LNKParser) -> io::Result<()>;
+
+    /// Write the buffered records and the end of the output.
+    fn finish(self: Box<Self>) -> io::Result<()>;
+}
+
+/// The options that add data to the records.
+pub struct RecordOptions {
+    pub normalize: bool,
+    pub analyze: bool,
+    pub rules: Option<RuleSet>,
+}
+
+impl RecordOptions {
+    fn findings(&self, parsed: &LNKParser) -> Option<Vec<Finding>> {
+        if self.analyze {
+            Some(parsed.analyze())
+        } else {
+            None
+        }
+    }
+
+    fn rule_matches(&self, parsed: &LNKParser) -> Option<Vec<RuleMatch>> {
+        self.rules.as_ref().map(|rules| rules.evaluate(parsed))
+    }
+}
+
+/// A buffered output stream (stdout or a file) that is flushed every `FLUSH_INTERVAL` records.
+pub struct OutputStream {
+    writer: BufWriter<Box<dyn Write + Send>>,
+    encoding: Encoding,
+    records: usize,
+}
+
+impl OutputStream {
+    /// Open the output, `output_to` is a file path or `stdout`. The text is written in `encoding`.
+    pub fn open(output_to: &str, encoding: Encoding) -> io::Result<Self> {
+        let w: Box<dyn Write + Send> = match output_to {
+            "stdout" => Box::new(io::stdout()),
+            _ => Box::new(File::create(output_to)?),
+        };
+        Self::new(w, encoding)
+    }
+
+    /// Write the output to `w` in `encoding`.
+    pub fn new(w: Box<dyn Write + Send>, encoding: Encoding) -> io::Result<Self> {
+        let mut writer = BufWriter::new(w);
+        writer.write_all(encoding.bom())?;
+        Ok(Self {
+            writer,
+            encoding,
+            records: 0,
+        })
+    }
+
+    fn write_all(&mut self, data: &str) -> io::Result<()> {
+        match self.encoding {
+            Encoding::Utf16Le => self.writer.write_all(&self.encoding.encode(data)),
+            _ => self.writer.write_all(data.as_bytes()),
+        }
+    }
+
+    /// Write a record and flush the output periodically.
+    fn write_record(&mut self, data: &str) -> io::Result<()> {
+        self.write_all(data)?;
+        self.records += 1;
+        if self.records.is_multiple_of(FLUSH_INTERVAL) {
+            self.writer.flush()?;
+        }
+        Ok(())
+    }
+
+    fn finish(mut self) -> io::Result<()> {
+        self.writer.flush()
+    }
+
+    /// The unbuffered output, used by the writers that do their own buffering.
+    #[cfg(feature = "parquet")]
+    fn into_inner(self) -> io::Result<Box<dyn Write + Send>> {
+        self.writer.into_inner().map_err(|e| e.into_error())
+    }
+}
+
+#[derive(Debug, Serialize)]
+#[serde(untagged)]
+pub enum JsonRecord {
+    Raw(Box<LNKParser>),
+    Normalize(Box<NormalizedRecord>),
+}
+
+/// The parsed file (or its normalized record) with the analysis findings if requested.
+#[derive(Debug, Serialize)]
+pub struct OutputRecord {
+    #[serde(flatten)]
+    pub record: JsonRecord,
+    /// The decoded command line arguments of the parsed file, not part of the normalized record.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub decoded_arguments: Option<DecodedArguments>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub findings: Option<Vec<Finding>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub rule_matches: Option<Vec<RuleMatch>>,
+}
+
+impl OutputRecord {
+    pub fn new(parsed: LNKParser, normalize: bool, options: &RecordOptions) -> Self {
+        let findings = options.findings(&parsed);
+        let rule_matches = options.rule_matches(&parsed);
+        let (record, decoded_arguments) = if normalize {
+            (
+                JsonRecord::Normalize(Box::new(parsed.to_normalized_record())),
+                None,
+            )
+        } else {
+            let decoded_arguments = parsed.deobfuscated_arguments();
+            (JsonRecord::Raw(Box::new(parsed)), decoded_arguments)
+        };
+        Self {
+            record,
+            decoded_arguments,
+            findings,
+            rule_matches,
+        }
+    }
+}
+
+/// A row per file with the normalized record fields, or the flattened parsed data.
+pub struct CsvOutput {
+    output: OutputStream,
+    options: RecordOptions,
+    format: CsvFormat,
+    flatten: bool,
+    headers: bool,
+    /// The written columns, not set when the columns of the flattened data are not selected.
+    columns: Option<Vec<String>>,
+    /// The flattened rows, kept until all the column names are known. All the parsed files are
+    /// kept in memory in this case, selecting the columns with `--fields` avoids it.
+    rows: Vec<HashMap<String, String>>,
+    /// The column names of the flattened rows in the order they first appear.
+    row_columns: Vec<String>,
+}
+
+impl CsvOutput {
+    /// `fields` selects the written columns (all the columns if not set), `flatten` writes the
+    /// parsed data with dotted field names instead of the normalized record.
+    pub fn new(
+        mut output: OutputStream,
+        options: RecordOptions,
+        format: CsvFormat,
+        fields: Option<Vec<String>>,
+        flatten: bool,
+        headers: bool,
+    ) -> io::Result<Self> {
+        let columns = if flatten {
+            fields
+        } else {
+            let mut available = NormalizedRecord::FIELDS.to_vec();
+            if options.analyze {
+                available.extend(["findings", "severity"]);
+            }
+            if options.rules.is_some() {
+                available.push("rule_matches");
+            }
+            if let Some(unknown) = fields
+                .iter()
+                .flatten()
+                .find(|field| !available.contains(&field.as_str()))
+            {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidInput,
+                    format!(
+                        "Unknown CSV field '{}', the fields are: {}",
+                        unknown,
+                        available.join(", ")
+                    ),
+                ));
+            }
+            Some(fields.unwrap_or_else(|| available.iter().map(|f| f.to_string()).collect()))
+        };
+        if let (true, Some(columns)) = (headers, &columns) {
+            output.write_all(&format.row(columns)?)?;
+        }
+        Ok(Self {
+            output,
+            options,
+            format,
+            flatten,
+            headers,
+            columns,
+            rows: vec![],
+            row_columns: vec![],
+        })
+    }
+
+    fn record_fields(&self, parsed: LNKParser) -> io::Result<Vec<(String, String)>> {
+        if self.flatten {
+            let record = OutputRecord::new(parsed, self.options.normalize, &self.options);
+            return Ok(flatten(&record)?);
+        }
+        let mut fields = parsed
+            .to_normalized_record()
+            .fields()
+            .into_iter()
+            .map(|(name, value)| (name.to_string(), value))
+            .collect::<Vec<(String, String)>>();
+        if let Some(findings) = self.options.findings(&parsed) {
+            fields.push((
+                "findings".to_string(),
+                findings
+                    .iter()
+                    .map(|finding| finding.id)
+                    .collect::<Vec<&str>>()
+                    .join(","),
+            ));
+            fields.push((
+                "severity".to_string(),
+                findings
+                    .first()
+                    .map(|finding| finding.severity.to_string())
+                    .unwrap_or_default(),
+            ));
+        }
+        if let Some(rule_matches) = self.options.rule_matches(&parsed) {
+            fields.push((
+                "rule_matches".to_string(),
+                rule_matches
+                    .iter()
+                    .map(|rule_match| rule_match.id.as_str())
+                    .collect::<Vec<&str>>()
+                    .join(","),
+            ));
+        }
+        Ok(fields)
+    }
+}
+
+fn write_csv_row(
+    output: &mut OutputStream,
+    format: &CsvFormat,
+    columns: &[String],
+    fields: &HashMap<String, String>,
+) -> io::Result<()> {
+    let values = columns
+        .iter()
+        .map(|column| fields.get(column).map(String::as_str).unwrap_or_default())
+        .collect::<Vec<&str>>();
+    output.write_record(&format.row(&values)?)
+}
+
+impl OutputWriter for CsvOutput {
+    fn write(&mut self, _path: &str, parsed: LNKParser) -> io::Result<()> {
+        let fields = self.record_fields(parsed)?;
+        match &self.columns {
+            Some(columns) => write_csv_row(
+                &mut self.output,
+                &self.format,
+                columns,
+                &fields.into_iter().collect(),
+            ),
+            None => {
+                for (name, _) in &fields {
+                    if !self.row_columns.contains(name) {
+                        self.row_columns.push(name.to_owned());
+                    }
+                }
+                self.rows.push(fields.into_iter().collect());
+                Ok(())
+            }
+        }
+    }
+
+    fn finish(mut self: Box<Self>) -> io::Result<()> {
+        if self.columns.is_none() {
+            let columns = std::mem::take(&mut self.row_columns);
+            if self.headers {
+                let header = self.format.row(&columns)?;
+                self.output.write_all(&header)?;
+            }
+            for row in &self.rows {
+                write_csv_row(&mut self.output, &self.format, &columns, row)?;
+            }
+        }
+        self.output.finish()
+    }
+}
+
+/// A JSON array, the records are written as they are parsed.
+pub struct JsonOutput {
+    output: OutputStream,
+    options: RecordOptions,
+    records: usize,
+}
+
+impl JsonOutput {
+    pub fn new(output: OutputStream, options: RecordOptions) -> Self {
+        Self {
+            output,
+            options,
+            records: 0,
+        }
+    }
+}
+
+impl OutputWriter for JsonOutput {
+    fn write(&mut self, _path: &str, parsed: LNKParser) -> io::Result<()> {
+        let record = OutputRecord::new(parsed, self.options.normalize, &self.options);
+        let json_data = serde_json::to_string(&record)?;
+        let separator = if self.records == 0 { "[" } else { "," };
+        self.records += 1;
+        self.output
+            .write_record(&format!("{}{}", separator, json_data))
+    }
+
+    fn finish(mut self: Box<Self>) -> io::Result<()> {
+        let end = if self.records == 0 { "[]" } else { "]" };
+        self.output.write_all(end)?;
+        self.output.finish()
+    }
+}
+
+/// A JSON object per line.
+pub struct JsonlOutput {
+    output: OutputStream,
+    options: RecordOptions,
+}
+
+impl JsonlOutput {
+    pub fn new(output: OutputStream, options: RecordOptions) -> Self {
+        Self { output, options }
+    }
+}
+
+impl OutputWriter for JsonlOutput {
+    fn write(&mut self, _path: &str, parsed: LNKParser) -> io::Result<()> {
+        let record = OutputRecord::new(parsed, self.options.normalize, &self.options);
+        let json_data = serde_json::to_string(&record)?;
+        self.output.write_record(&format!("{}\r\n", json_data))
+    }
+
+    fn finish(self: Box<Self>) -> io::Result<()> {
+        self.output.finish()
+    }
+}
+
+/// Sleuth Kit bodyfile lines of the LNK file and its target.
+pub struct BodyfileOutput {
+    output: OutputStream,
+}
+
+impl BodyfileOutput {
+    pub fn new(output: OutputStream) -> Self {
+        Self { output }
+    }
+}
+
+impl OutputWriter for BodyfileOutput {
+    fn write(&mut self, _path: &str, parsed: LNKParser) -> io::Result<()> {
+        for entry in parsed.bodyfile_entries() {
+            self.output.write_record(&format!("{}\n", entry))?;
+        }
+        Ok(())
+    }
+
+    fn finish(self: Box<Self>) -> io::Result<()> {
+        self.output.finish()
+    }
+}
+
+/// A TLN line per timestamp.
+pub struct TlnOutput {
+    output: OutputStream,
+}
+
+impl TlnOutput {
+    pub fn new(output: OutputStream) -> Self {
+        Self { output }
+    }
+}
+
+impl OutputWriter for TlnOutput {
+    fn write(&mut self, _path: &str, parsed: LNKParser) -> io::Result<()> {
+        for event in parsed.timeline_events() {
+            self.output.write_record(&format!("{}\n", event.to_tln()))?;
+        }
+        Ok(())
+    }
+
+    fn finish(self: Box<Self>) -> io::Result<()> {
+        self.output.finish()
+    }
+}
+
+/// A CSV row per timestamp, the events of all the files are sorted by time when finished.
+pub struct TimelineOutput {
+    output: OutputStream,
+    format: CsvFormat,
+    events: Vec<TimelineEvent>,
+}
+
+impl TimelineOutput {
+    pub fn new(mut output: OutputStream, format: CsvFormat, headers: bool) -> io::Result<Self> {
+        if headers {
+            output.write_all(&format.row(&TimelineEvent::FIELDS)?)?;
+        }
+        Ok(Self {
+            output,
+            format,
+            events: vec![],
+        })
+    }
+}
+
+impl OutputWriter for TimelineOutput {
+    fn write(&mut self, _path: &str, parsed: LNKParser) -> io::Result<()> {
+        self.events.extend(parsed.timeline_events());
+        Ok(())
+    }
+
+    fn finish(mut self: Box<Self>) -> io::Result<()> {
+        self.events.sort_by_key(|event| event.timestamp);
+        for event in &self.events {
+            let values = event
+                .fields()
+                .into_iter()
+                .map(|(_, value)| value)
+                .collect::<Vec<String>>();
+            let row = self.format.row(&values)?;
+            self.output.write_record(&row)?;
+        }
+        self.output.finish()
+    }
+}
+
+/// Elastic Common Schema documents in the Elasticsearch bulk API format.
+pub struct EcsOutput {
+    output: OutputStream,
+    options: RecordOptions,
+}
+
+impl EcsOutput {
+    pub fn new(output: OutputStream, options: RecordOptions) -> Self {
+        Self { output, options }
+    }
+}
+
+impl OutputWriter for EcsOutput {
+    fn write(&mut self, _path: &str, parsed: LNKParser) -> io::Result<()> {
+        let json_data = serde_json::to_string(&EcsRecord::new(parsed, &self.options))?;
+        // a bulk API action line before every document
+        self.output
+            .write_record(&format!("{{\"create\":{{}}}}\n{}\n", json_data))
+    }
+
+    fn finish(self: Box<Self>) -> io::Result<()> {
+        self.output.finish()
+    }
+}
+
+/// The parsed files in a SQLite database.
+#[cfg(feature = "sqlite")]
+pub struct SqliteOutput {
+    writer: SqliteWriter,
+    options: RecordOptions,
+}
+
+#[cfg(feature = "sqlite")]
+impl SqliteOutput {
+    pub fn new(path: &str, options: RecordOptions) -> io::Result<Self> {
+        Ok(Self {
+            writer: SqliteWriter::create(path)?,
+            options,
+        })
+    }
+}
+
+#[cfg(feature = "sqlite")]
+impl OutputWriter for SqliteOutput {
+    fn write(&mut self, _path: &str, parsed: LNKParser) -> io::Result<()> {
+        let findings = self.options.findings(&parsed).unwrap_or_default();
+        let id = self.writer.write(&parsed, &findings)?;
+        for rule_match in self.options.rule_matches(&parsed).unwrap_or_default() {
+            self.writer
+                .write_rule_match(id, &rule_match.id, rule_match.description.as_deref())?;
+        }
+        Ok(())
+    }
+
+    fn finish(self: Box<Self>) -> io::Result<()> {
+        self.writer.finish().map(|_| ())
+    }
+}
+
+/// The normalized records in a Parquet file.
+#[cfg(feature = "parquet")]
+pub struct ParquetOutput {
+    writer: ParquetWriter<Box<dyn Write + Send>>,
+    options: RecordOptions,
+}
+
+#[cfg(feature = "parquet")]
+impl ParquetOutput {
+    pub fn new(output: OutputStream, options: RecordOptions) -> io::Result<Self> {
+        Ok(Self {
+            writer: ParquetWriter::new(output.into_inner()?)?,
+            options,
+        })
+    }
+}
+
+#[cfg(feature = "parquet")]
+impl OutputWriter for ParquetOutput {
+    fn write(&mut self, _path: &str, parsed: LNKParser) -> io::Result<()> {
+        let findings = self.options.findings(&parsed);
+        let rule_matches = self.options.rule_matches(&parsed);
+        let rule_ids = rule_matches.as_ref().map(|rule_matches| {
+            rule_matches
+                .iter()
+                .map(|rule_match| rule_match.id.as_str())
+                .collect::<Vec<&str>>()
+        });
+        self.writer
+            .write(&parsed, findings.as_deref(), rule_ids.as_deref())
+    }
+
+    fn finish(self: Box<Self>) -> io::Result<()> {
+        self.writer.finish()?.flush()
+    }
+}
+
+/// A self-contained HTML report of all the parsed files, written when finished.
+pub struct HtmlOutput {
+    output: OutputStream,
+    options: RecordOptions,
+    report: HtmlReport,
+}
+
+impl HtmlOutput {
+    pub fn new(output: OutputStream, options: RecordOptions) -> Self {
+        Self {
+            output,
+            options,
+            report: HtmlReport::default(),
+        }
+    }
+}
+
+impl OutputWriter for HtmlOutput {
+    fn write(&mut self, _path: &str, parsed: LNKParser) -> io::Result<()> {
+        let rule_matches = self.options.rule_matches(&parsed).unwrap_or_default();
+        self.report.add(parsed, rule_matches)?;
+        Ok(())
+    }
+
+    fn finish(mut self: Box<Self>) -> io::Result<()> {
+        let html = self.report.render(Utc::now());
+        self.output.write_all(&html)?;
+        self.output.finish()
+    }
+}
+
+/// LNK files that share the same structural fingerprint.
+#[derive(Debug, Serialize)]
+struct FingerprintGroup {
+    fingerprint: String,
+    count: usize,
+    features: FingerprintFeatures,
+    files: Vec<String>,
+}
+
+/// The parsed files grouped by their structural fingerprint, written when finished.
+pub struct FingerprintGroupsOutput {
+    output: OutputStream,
+    output_format: OutputFormat,
+    csv_format: CsvFormat,
+    groups: HashMap<String, FingerprintGroup>,
+}
+
+impl FingerprintGroupsOutput {
+    pub fn new(
+        mut output: OutputStream,
+        output_format: OutputFormat,
+        csv_format: CsvFormat,
+        headers: bool,
+    ) -> io::Result<Self> {
+        if headers {
+            if let OutputFormat::CSV = output_format {
+                output.write_all(&csv_format.row(&["fingerprint", "count", "lnk_full_path"])?)?;
+            }
+        }
+        Ok(Self {
+            output,
+            output_format,
+            csv_format,
+            groups: HashMap::new(),
+        })
+    }
+}
+
+impl OutputWriter for FingerprintGroupsOutput {
+    fn write(&mut self, path: &str, parsed: LNKParser) -> io::Result<()> {
+        let fingerprint = parsed.fingerprint();
+        let lnk_full_path = match parsed.get_lnk_file_metadata() {
+            Some(metadata) => metadata.get_full_path().to_owned(),
+            None => path.to_string(),
+        };
+        let group = self
+            .groups
+            .entry(fingerprint.hash.to_owned())
+            .or_insert_with(|| FingerprintGroup {
+                fingerprint: fingerprint.hash,
+                count: 0,
+                features: fingerprint.features,
+                files: vec![],
+            });
+        group.count += 1;
+        group.files.push(lnk_full_path);
+        Ok(())
+    }
+
+    fn finish(mut self: Box<Self>) -> io::Result<()> {
+        let mut groups = self
+            .groups
+            .drain()
+            .map(|(_, group)| group)
+            .collect::<Vec<FingerprintGroup>>();
+        // the biggest clusters first
+        groups.sort_by(|a, b| {
+            b.count
+                .cmp(&a.count)
+                .then_with(|| a.fingerprint.cmp(&b.fingerprint))
+        });
+        match self.output_format {
+            OutputFormat::JSON => {
+                let json_data = serde_json::to_string(&groups)?;
+                self.output.write_all(&json_data)?;
+            }
+            OutputFormat::JSONL => {
+                for group in groups {
+                    let json_data = serde_json::to_string(&group)?;
+                    self.output.write_record(&format!("{}\r\n", json_data))?;
+                }
+            }
+            _ => {
+                for group in groups {
+                    for file in &group.files {
+                        let row = self.csv_format.row(&[
+                            group.fingerprint.to_owned(),
+                            group.count.to_string(),
+                            file.to_owned(),
+                        ])?;
+                        self.output.write_record(&row)?;
+                    }
+                }
+            }
+        }
+        self.output.finish()
+    }
+}
+
+/// The IOCs extracted from the parsed files, the JSON output format is a STIX 2.1 bundle.
+pub struct IocsOutput {
+    output: OutputStream,
+    output_format: OutputFormat,
+    csv_format: CsvFormat,
+    /// The IOCs of the STIX bundle.
+    iocs: Vec<TaggedIoc>,
+}
+
+impl IocsOutput {
+    pub fn new(
+        mut output: OutputStream,
+        output_format: OutputFormat,
+        csv_format: CsvFormat,
+        headers: bool,
+    ) -> io::Result<Self> {
+        if headers {
+            if let OutputFormat::CSV = output_format {
+                output.write_all(&csv_format.row(&[
+                    "lnk_full_path",
+                    "type",
+                    "value",
+                    "source",
+                ])?)?;
+            }
+        }
+        Ok(Self {
+            output,
+            output_format,
+            csv_format,
+            iocs: vec![],
+        })
+    }
+}
+
+impl OutputWriter for IocsOutput {
+    fn write(&mut self, _path: &str, parsed: LNKParser) -> io::Result<()> {
+        let iocs = parsed.iocs();
+        match self.output_format {
+            OutputFormat::JSON => self.iocs.extend(iocs),
+            OutputFormat::JSONL => {
+                for ioc in iocs {
+                    let json_data = serde_json::to_string(&ioc)?;
+                    self.output.write_record(&format!("{}\r\n", json_data))?;
+                }
+            }
+            _ => {
+                for ioc in iocs {
+                    let row = self.csv_format.row(&[
+                        ioc.lnk_full_path.to_owned().unwrap_or_default(),
+                        ioc.ioc_type.as_str().to_string(),
+                        ioc.value,
+                        ioc.source,
+                    ])?;
+                    self.output.write_record(&row)?;
+                }
+            }
+        }
+        Ok(())
+    }
+
+    fn finish(mut self: Box<Self>) -> io::Result<()> {
+        if let OutputFormat::JSON = self.output_format {
+            let json_data = to_stix_bundle(&self.iocs, Utc::now()).to_string();
+            self.output.write_all(&json_data)?;
+        }
+        self.output.finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::Value;
+    use std::sync::{Arc, Mutex};
+
+    /// A writer that keeps the written data and counts the flushes.
+    #[derive(Clone, Default)]
+    struct SharedBuffer {
+        data: Arc<Mutex<Vec<u8>>>,
+        flushes: Arc<Mutex<usize>>,
+    }
+
+    impl SharedBuffer {
+        fn output(&self, encoding: Encoding) -> OutputStream {
+            OutputStream::new(Box::new(self.clone()), encoding).unwrap()
+        }
+
+        fn data(&self) -> Vec<u8> {
+            self.data.lock().unwrap().clone()
+        }
+
+        fn text(&self) -> String {
+            String::from_utf8(self.data()).unwrap()
+        }
+
+        fn flushes(&self) -> usize {
+            *self.flushes.lock().unwrap()
+        }
+    }
+
+    impl Write for SharedBuffer {
+        fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+            self.data.lock().unwrap().extend_from_slice(buf);
+            Ok(buf.len())
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            *self.flushes.lock().unwrap() += 1;
+            Ok(())
+        }
+    }
+
+    fn options() -> RecordOptions {
+        RecordOptions {
+            normalize: false,
+            analyze: false,
+            rules: None,
+        }
+    }
+
+    fn parse(path: &str) -> LNKParser {
+        LNKParser::from_path(path).unwrap()
+    }
+
+    const SAMPLES: [&str; 3] = [
+        "samples/mal/exec_cmd_powershell.lnk",
+        "samples/WIN7/6.1_7601/network_share.lnk",
+        "samples/other/non_unicode_strings.lnk",
+    ];
+
+    #[test]
+    fn test_json_output_empty() {
+        let buffer = SharedBuffer::default();
+        let output = Box::new(JsonOutput::new(buffer.output(Encoding::Utf8), options()));
+        output.finish().unwrap();
+        assert_eq!(buffer.text(), "[]");
+    }
+
+    #[test]
+    fn test_json_output_records() {
+        let buffer = SharedBuffer::default();
+        let mut output = Box::new(JsonOutput::new(buffer.output(Encoding::Utf8), options()));
+        for path in SAMPLES.iter() {
+            output.write(path, parse(path)).unwrap();
+        }
+        output.finish().unwrap();
+        let records = serde_json::from_str::<Value>(&buffer.text()).unwrap();
+        let records = records.as_array().unwrap();
+        assert_eq!(records.len(), SAMPLES.len());
+        for (record, path) in records.iter().zip(SAMPLES.iter()) {
+            let full_path = record["lnk_file_metadata"]["full_path"].as_str().unwrap();
+            assert!(full_path.ends_with(path.rsplit('/').next().unwrap()));
+        }
+    }
+
+    #[test]
+    fn test_output_stream_flush_interval() {
+        let buffer = SharedBuffer::default();
+        let mut output = buffer.output(Encoding::Utf8);
+        for _ in 0..FLUSH_INTERVAL - 1 {
+            output.write_record("record\n").unwrap();
+        }
+        assert_eq!(buffer.flushes(), 0);
+        // the records are buffered until the flush
+        assert!(buffer.data().is_empty());
+        output.write_record("record\n").unwrap();
+        assert_eq!(buffer.flushes(), 1);
+        assert_eq!(buffer.text(), "record\n".repeat(FLUSH_INTERVAL));
+        for _ in 0..FLUSH_INTERVAL {
+            output.write_record("record\n").unwrap();
+        }
+        assert_eq!(buffer.flushes(), 2);
+        output.finish().unwrap();
+        assert_eq!(buffer.flushes(), 3);
+    }
+
+    #[test]
+    fn test_csv_output_unknown_field() {
+        let buffer = SharedBuffer::default();
+        let fields = Some(vec!["target_full_path".to_string(), "target".to_string()]);
+        let result = CsvOutput::new(
+            buffer.output(Encoding::Utf8),
+            options(),
+            CsvFormat::default(),
+            fields,
+            false,
+            true,
+        );
+        let error = result.err().unwrap();
+        assert_eq!(error.kind(), io::ErrorKind::InvalidInput);
+        assert!(error.to_string().contains("'target'"));
+    }
+
+    #[test]
+    fn test_csv_output_fields() {
+        let buffer = SharedBuffer::default();
+        let fields = Some(vec![
+            "command_line_arguments".to_string(),
+            "target_size".to_string(),
+        ]);
+        let format = CsvFormat {
+            delimiter: b'\t',
+            crlf: false,
+            encoding: Encoding::Utf16Le,
+        };
+        let output = OutputStream::new(Box::new(buffer.clone()), format.encoding).unwrap();
+        let mut output =
+            Box::new(CsvOutput::new(output, options(), format, fields, false, true).unwrap());
+        output.write(SAMPLES[0], parse(SAMPLES[0])).unwrap();
+        output.finish().unwrap();
+        let data = buffer.data();
+        assert_eq!(data[..2], [0xFF, 0xFE]);
+        let text = String::from_utf16(
+            &data[2..]
+                .chunks(2)
+                .map(|c| u16::from_le_bytes([c[0], c[1]]))
+                .collect::<Vec<u16>>(),
+        )
+        .unwrap();
+        assert_eq!(
+            text,
+            "\"command_line_arguments\"\t\"target_size\"\n\"/c powershell.exe -c \"\"Write-Host This is a test\"\"\"\t\"331776\"\n"
+        );
+    }
+
+    #[test]
+    fn test_csv_output_flatten() {
+        let buffer = SharedBuffer::default();
+        let mut output = Box::new(
+            CsvOutput::new(
+                buffer.output(Encoding::Utf8),
+                options(),
+                CsvFormat::default(),
+                None,
+                true,
+                true,
+            )
+            .unwrap(),
+        );
+        for path in SAMPLES.iter() {
+            output.write(path, parse(path)).unwrap();
+        }
+        output.finish().unwrap();
+        let text = buffer.text();
+        let lines = text.split_terminator("\r\n").collect::<Vec<&str>>();
+        assert_eq!(lines.len(), SAMPLES.len() + 1);
+        let header = lines[0]
+            .split(',')
+            .map(|column| column.trim_matches('"'))
+            .collect::<Vec<&str>>();
+        // the columns are in the structure order and are written once
+        let position = |column: &str| header.iter().position(|c| *c == column).unwrap();
+        assert_eq!(header[0], "target_full_path");
+        assert!(position("shell_link_header.file_size") < position("link_info.local_base_path"));
+        assert!(
+            position("link_info.local_base_path")
+                < position("link_info.common_network_relative_link.net_name")
+        );
+        let mut unique = header.clone();
+        unique.sort_unstable();
+        unique.dedup();
+        assert_eq!(unique.len(), header.len());
+    }
+}
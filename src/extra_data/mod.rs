@@ -5,7 +5,7 @@ mod tracker_data_block;
 use byteorder::{LittleEndian, ReadBytesExt};
 use serde::Serialize;
 use std::io::{Cursor, Read, Seek};
-use tracker_data_block::TrackerDataBlock;
+pub use tracker_data_block::{DroidInfo, TrackerDataBlock, TrackerMoveAnalysis, TrackerMoveStatus};
 use winparsingtools::ReaderError;
 
 /// ExtraData types implemented
@@ -15,11 +15,45 @@ pub enum ExtraDataTypes {
     Tracker(TrackerDataBlock),
 }
 
+/// The location, size and signature of an ExtraData block (including the blocks that are not implemented).
+#[derive(Debug, Serialize, Clone, PartialEq, Eq)]
+pub struct ExtraDataBlockHeader {
+    /// The absolute offset of the block in the parsed data.
+    pub offset: u64,
+    pub size: u32,
+    pub signature: u32,
+    /// Printable ASCII and UTF-16LE strings found in the data of the blocks that are not implemented.
+    pub strings: Vec<String>,
+}
+
+impl ExtraDataBlockHeader {
+    /// Returns the block name as defined in the specification based on its signature.
+    pub fn name(&self) -> &'static str {
+        match self.signature {
+            0xA0000001 => "EnvironmentVariableDataBlock",
+            0xA0000002 => "ConsoleDataBlock",
+            0xA0000003 => "TrackerDataBlock",
+            0xA0000004 => "ConsoleFEDataBlock",
+            0xA0000005 => "SpecialFolderDataBlock",
+            0xA0000006 => "DarwinDataBlock",
+            0xA0000007 => "IconEnvironmentDataBlock",
+            0xA0000008 => "ShimDataBlock",
+            0xA0000009 => "PropertyStoreDataBlock",
+            0xA000000B => "KnownFolderDataBlock",
+            0xA000000C => "VistaAndAboveIDListDataBlock",
+            _ => "UnknownDataBlock",
+        }
+    }
+}
+
 /// ExtraData refers to a set of structures that convey additional information about a link target.
 /// These optional structures can be present in an extra data section that is appended to the basic Shell Link Binary File Format.
 #[derive(Debug, Serialize)]
 pub struct ExtraData {
     pub extra_data_blocks: Vec<ExtraDataTypes>,
+    /// The headers of all the blocks in the order they appear in the file.
+    #[serde(skip_serializing)]
+    pub blocks: Vec<ExtraDataBlockHeader>,
 }
 
 impl ExtraData {
@@ -29,7 +63,9 @@ impl ExtraData {
 
     pub fn from_reader<R: Read + Seek>(r: &mut R) -> Result<Self, ReaderError> {
         let mut extra_data_blocks: Vec<ExtraDataTypes> = Vec::new();
+        let mut blocks = Vec::new();
         loop {
+            let offset = r.stream_position()?;
             let size = r.read_u32::<LittleEndian>()?;
             if size == 0 {
                 break;
@@ -37,13 +73,71 @@ impl ExtraData {
             let signature = r.read_u32::<LittleEndian>()?;
             let mut extra_data_bytes = vec![0; (size - 8) as usize];
             r.read_exact(&mut extra_data_bytes)?;
+            let mut strings = vec![];
             if signature == 0xa0000003 {
                 extra_data_blocks.push(ExtraDataTypes::Tracker(TrackerDataBlock::from_buffer(
                     &extra_data_bytes,
                 )?));
+            } else {
+                strings = printable_strings(&extra_data_bytes);
             }
+            blocks.push(ExtraDataBlockHeader {
+                offset,
+                size,
+                signature,
+                strings,
+            });
         }
 
-        Ok(Self { extra_data_blocks })
+        Ok(Self {
+            extra_data_blocks,
+            blocks,
+        })
+    }
+
+    /// Returns the first TrackerDataBlock if available.
+    #[allow(clippy::unnecessary_find_map)]
+    pub fn tracker(&self) -> Option<&TrackerDataBlock> {
+        self.extra_data_blocks.iter().find_map(|edb| match edb {
+            ExtraDataTypes::Tracker(tracker) => Some(tracker),
+        })
+    }
+}
+
+/// The minimum number of characters of the strings returned by `printable_strings`.
+const MIN_STRING_LENGTH: usize = 4;
+
+/// Extract the printable ASCII and UTF-16LE strings from the data.
+fn printable_strings(data: &[u8]) -> Vec<String> {
+    let mut strings = vec![];
+    let mut push = |current: &mut String| {
+        if current.chars().count() >= MIN_STRING_LENGTH {
+            strings.push(current.to_owned());
+        }
+        current.clear();
+    };
+
+    let mut current = String::new();
+    for b in data {
+        if (0x20..0x7F).contains(b) {
+            current.push(*b as char);
+        } else {
+            push(&mut current);
+        }
+    }
+    push(&mut current);
+
+    // UTF-16LE strings at both alignments
+    for start in 0..2 {
+        for chunk in data[start.min(data.len())..].chunks_exact(2) {
+            let c = char::from_u32(u16::from_le_bytes([chunk[0], chunk[1]]) as u32);
+            match c {
+                Some(c) if (c.is_ascii_graphic() || c == ' ') && chunk[1] == 0 => current.push(c),
+                Some(c) if !c.is_ascii() && c.is_alphanumeric() => current.push(c),
+                _ => push(&mut current),
+            }
+        }
+        push(&mut current);
     }
+    strings
 }
@@ -1,8 +1,90 @@
+use crate::normalized_record::TIMESTAMP_FORMAT;
 use byteorder::{LittleEndian, ReadBytesExt};
+use chrono::{DateTime, Duration, NaiveDate, Utc};
 use serde::{ser::SerializeStruct, Serialize};
 use std::io::{Cursor, Read};
 use winparsingtools::{structs::Guid, utils::read_cp1252_string, ReaderError};
 
+/// The decoded fields of a version 1 UUID droid. Version 1 UUIDs contain the time the object ID was
+/// assigned, a clock sequence and the MAC address of the machine that assigned it.
+#[derive(Debug, Serialize, Clone, PartialEq, Eq)]
+pub struct DroidInfo {
+    pub time: DateTime<Utc>,
+    pub clock_sequence: u16,
+    pub mac_address: String,
+}
+
+impl DroidInfo {
+    /// Decode the droid, returns `None` if the GUID is not a version 1 UUID.
+    pub fn from_guid(guid: &Guid) -> Option<Self> {
+        // XXXXXXXX-XXXX-XXXX-XXXX-XXXXXXXXXXXX
+        let uuid = guid.to_string();
+        let parts = uuid.split('-').collect::<Vec<&str>>();
+        if parts.len() != 5 || parts[4].len() != 12 {
+            return None;
+        }
+        let time_low = u64::from_str_radix(parts[0], 16).ok()?;
+        let time_mid = u64::from_str_radix(parts[1], 16).ok()?;
+        let time_hi_and_version = u64::from_str_radix(parts[2], 16).ok()?;
+        let clock_seq = u16::from_str_radix(parts[3], 16).ok()?;
+
+        if time_hi_and_version >> 12 != 1 {
+            return None;
+        }
+
+        // 60-bit timestamp in 100-nanosecond intervals since 1582-10-15 (the Gregorian calendar reform)
+        let timestamp = ((time_hi_and_version & 0x0FFF) << 48) | (time_mid << 32) | time_low;
+        // Unwraping here is safe because the date is always valid.
+        let time = NaiveDate::from_ymd_opt(1582, 10, 15)
+            .unwrap()
+            .and_hms_opt(0, 0, 0)
+            .unwrap()
+            .and_utc()
+            + Duration::microseconds((timestamp / 10) as i64);
+
+        let mac_address = parts[4]
+            .chars()
+            .collect::<Vec<_>>()
+            .chunks(2)
+            .map(|chunk| chunk.iter().collect::<String>())
+            .collect::<Vec<_>>()
+            .join(":")
+            .to_uppercase();
+
+        Some(Self {
+            time,
+            // the two most significant bits are the variant
+            clock_sequence: clock_seq & 0x3FFF,
+            mac_address,
+        })
+    }
+}
+
+/// The result of comparing the current droids with the birth droids.
+#[derive(Debug, Serialize, Clone, Copy, PartialEq, Eq)]
+pub enum TrackerMoveStatus {
+    /// The current droids are identical to the birth droids.
+    Untouched,
+    /// The file object ID changed while the volume stayed the same.
+    FileDroidChanged,
+    /// The target was moved to another volume.
+    MovedAcrossVolumes,
+    /// The file object ID was assigned by a different machine (different MAC address node).
+    MovedAcrossMachines,
+}
+
+/// Compares the current droids with the birth droids to detect if the link target was moved.
+#[derive(Debug, Serialize, Clone, Copy, PartialEq, Eq)]
+pub struct TrackerMoveAnalysis {
+    pub status: TrackerMoveStatus,
+    /// `volume_droid` is different from `volume_droid_birth`.
+    pub volume_changed: bool,
+    /// `file_droid` is different from `file_droid_birth`.
+    pub file_droid_changed: bool,
+    /// `file_droid` and `file_droid_birth` are version 1 UUIDs with different MAC addresses.
+    pub machine_changed: bool,
+}
+
 #[derive(Debug)]
 pub struct TrackerDataBlock {
     pub size: u32,
@@ -42,27 +124,58 @@ impl TrackerDataBlock {
     }
 
     pub fn get_mac_address(&self) -> String {
-        // Ensure the UUID is valid and in the correct format
-        let uuid = self.file_droid.to_string();
-
-        // Check if the UUID is Version 1 (13th character must be '1')
-        if uuid.len() == 36 && uuid.chars().nth(14) == Some('1') {
-            // Extract the node portion (last 12 characters of the UUID)
-            if let Some(node_part) = uuid.get(24..36) {
-                // Split into MAC address format (every 2 characters separated by ':')
-                return node_part
-                    .chars()
-                    .collect::<Vec<_>>()
-                    .chunks(2)
-                    .map(|chunk| chunk.iter().collect::<String>())
-                    .collect::<Vec<_>>()
-                    .join(":")
-                    .to_uppercase();
-            }
+        match self.file_droid_info() {
+            Some(droid_info) => droid_info.mac_address,
+            // Return default MAC address if not Version 1 or invalid format
+            None => "00:00:00:00:00:00".to_string(),
+        }
+    }
+
+    /// Compare the current droids with the birth droids.
+    pub fn move_analysis(&self) -> TrackerMoveAnalysis {
+        let volume_changed = self.volume_droid.to_string() != self.volume_droid_birth.to_string();
+        let file_droid_changed = self.file_droid.to_string() != self.file_droid_birth.to_string();
+        let machine_changed = match (self.file_droid_info(), self.file_droid_birth_info()) {
+            (Some(current), Some(birth)) => current.mac_address != birth.mac_address,
+            _ => false,
+        };
+
+        let status = if machine_changed {
+            TrackerMoveStatus::MovedAcrossMachines
+        } else if volume_changed {
+            TrackerMoveStatus::MovedAcrossVolumes
+        } else if file_droid_changed {
+            TrackerMoveStatus::FileDroidChanged
+        } else {
+            TrackerMoveStatus::Untouched
+        };
+
+        TrackerMoveAnalysis {
+            status,
+            volume_changed,
+            file_droid_changed,
+            machine_changed,
         }
+    }
+
+    /// Decoded `file_droid` if it is a version 1 UUID.
+    pub fn file_droid_info(&self) -> Option<DroidInfo> {
+        DroidInfo::from_guid(&self.file_droid)
+    }
+
+    /// Decoded `file_droid_birth` if it is a version 1 UUID.
+    pub fn file_droid_birth_info(&self) -> Option<DroidInfo> {
+        DroidInfo::from_guid(&self.file_droid_birth)
+    }
+
+    /// Decoded `volume_droid` if it is a version 1 UUID.
+    pub fn volume_droid_info(&self) -> Option<DroidInfo> {
+        DroidInfo::from_guid(&self.volume_droid)
+    }
 
-        // Return default MAC address if not Version 1 or invalid format
-        "00:00:00:00:00:00".to_string()
+    /// Decoded `volume_droid_birth` if it is a version 1 UUID.
+    pub fn volume_droid_birth_info(&self) -> Option<DroidInfo> {
+        DroidInfo::from_guid(&self.volume_droid_birth)
     }
 }
 
@@ -71,7 +184,7 @@ impl Serialize for TrackerDataBlock {
     where
         S: serde::Serializer,
     {
-        let mut state = serializer.serialize_struct("TrackerDataBlock", 5)?;
+        let mut state = serializer.serialize_struct("TrackerDataBlock", 19)?;
 
         state.serialize_field("machine_id", &self.machine_id)?;
         state.serialize_field("file_droid", &self.file_droid)?;
@@ -79,6 +192,60 @@ impl Serialize for TrackerDataBlock {
         state.serialize_field("file_droid_birth", &self.file_droid_birth)?;
         state.serialize_field("volume_droid_birth", &self.volume_droid_birth)?;
         state.serialize_field("mac_address", &self.get_mac_address())?;
+        state.serialize_field("move_analysis", &self.move_analysis())?;
+
+        // (decoded droid, [time field, clock sequence field, MAC address field])
+        let droids = [
+            (
+                self.file_droid_info(),
+                [
+                    "file_droid_time",
+                    "file_droid_clock_sequence",
+                    "file_droid_mac_address",
+                ],
+            ),
+            (
+                self.volume_droid_info(),
+                [
+                    "volume_droid_time",
+                    "volume_droid_clock_sequence",
+                    "volume_droid_mac_address",
+                ],
+            ),
+            (
+                self.file_droid_birth_info(),
+                [
+                    "file_droid_birth_time",
+                    "file_droid_birth_clock_sequence",
+                    "file_droid_birth_mac_address",
+                ],
+            ),
+            (
+                self.volume_droid_birth_info(),
+                [
+                    "volume_droid_birth_time",
+                    "volume_droid_birth_clock_sequence",
+                    "volume_droid_birth_mac_address",
+                ],
+            ),
+        ];
+        for (droid_info, [time, clock_sequence, mac_address]) in droids {
+            match droid_info {
+                Some(droid_info) => {
+                    state.serialize_field(
+                        time,
+                        &droid_info.time.format(TIMESTAMP_FORMAT).to_string(),
+                    )?;
+                    state.serialize_field(clock_sequence, &droid_info.clock_sequence)?;
+                    state.serialize_field(mac_address, &droid_info.mac_address)?;
+                }
+                None => {
+                    state.skip_field(time)?;
+                    state.skip_field(clock_sequence)?;
+                    state.skip_field(mac_address)?;
+                }
+            }
+        }
 
         state.end()
     }
@@ -0,0 +1,177 @@
+//! Structural fingerprint of LNK files.
+//!
+//! LNK files generated by the same builder share the same structure (flags, sections, ExtraData blocks,
+//! tracker machine ID, etc) even when the target or the arguments are different. The fingerprint hashes
+//! these features so the files could be clustered, similar to `imphash` for PE files.
+
+use crate::{normalized_record::file_time, LNKParser};
+use md5::{Digest, Md5};
+use serde::Serialize;
+
+/// The structural features used to calculate the fingerprint.
+#[derive(Debug, Serialize, Clone, PartialEq, Eq)]
+pub struct FingerprintFeatures {
+    pub link_flags: Vec<String>,
+    pub file_attributes: Vec<String>,
+    /// The structures present in the file in the order they appear.
+    pub sections: Vec<&'static str>,
+    /// The ShellLinkHeader timestamps that are zeroed.
+    pub zeroed_header_times: Vec<&'static str>,
+    pub reserved0: u16,
+    pub reserved1: u32,
+    pub reserved2: u32,
+    pub show_command: u32,
+    pub icon_index: i32,
+    pub hot_key: String,
+    /// The class type of every shell item in the LinkTargetIDList.
+    pub id_list_item_types: Vec<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub link_info_header_size: Option<u32>,
+    /// The ExtraData blocks in the order they appear in the file formatted as `{name}:{size}`.
+    pub extra_data_blocks: Vec<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub machine_id: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub drive_serial_number: Option<String>,
+}
+
+impl FingerprintFeatures {
+    /// A stable textual representation of the features, one `name=value` per line.
+    pub fn canonical_string(&self) -> String {
+        let optional = |value: &Option<String>| value.to_owned().unwrap_or_default();
+        [
+            format!("link_flags={}", self.link_flags.join(",")),
+            format!("file_attributes={}", self.file_attributes.join(",")),
+            format!("sections={}", self.sections.join(",")),
+            format!("zeroed_header_times={}", self.zeroed_header_times.join(",")),
+            format!("reserved0={}", self.reserved0),
+            format!("reserved1={}", self.reserved1),
+            format!("reserved2={}", self.reserved2),
+            format!("show_command={}", self.show_command),
+            format!("icon_index={}", self.icon_index),
+            format!("hot_key={}", self.hot_key),
+            format!("id_list_item_types={}", self.id_list_item_types.join(",")),
+            format!(
+                "link_info_header_size={}",
+                self.link_info_header_size
+                    .map(|size| size.to_string())
+                    .unwrap_or_default()
+            ),
+            format!("extra_data_blocks={}", self.extra_data_blocks.join(",")),
+            format!("machine_id={}", optional(&self.machine_id)),
+            format!(
+                "drive_serial_number={}",
+                optional(&self.drive_serial_number)
+            ),
+        ]
+        .join("\n")
+    }
+}
+
+/// The structural fingerprint of a LNK file.
+#[derive(Debug, Serialize, Clone, PartialEq, Eq)]
+pub struct LnkFingerprint {
+    /// MD5 of the canonical string of the features.
+    pub hash: String,
+    pub features: FingerprintFeatures,
+}
+
+impl From<FingerprintFeatures> for LnkFingerprint {
+    fn from(features: FingerprintFeatures) -> Self {
+        Self {
+            hash: format!("{:x}", Md5::digest(features.canonical_string().as_bytes())),
+            features,
+        }
+    }
+}
+
+impl LNKParser {
+    /// Calculate the structural fingerprint of the LNK file.
+    pub fn fingerprint(&self) -> LnkFingerprint {
+        LnkFingerprint::from(self.fingerprint_features())
+    }
+
+    /// Extract the structural features used by `fingerprint`.
+    pub fn fingerprint_features(&self) -> FingerprintFeatures {
+        let header = self.get_shell_link_header();
+
+        let mut sections = vec!["ShellLinkHeader"];
+        if self.get_link_target_id_list().is_some() {
+            sections.push("LinkTargetIDList");
+        }
+        if self.get_link_info().is_some() {
+            sections.push("LinkInfo");
+        }
+        if self.get_name_string().is_some() {
+            sections.push("NameString");
+        }
+        if self.get_relative_path().is_some() {
+            sections.push("RelativePath");
+        }
+        if self.get_working_dir().is_some() {
+            sections.push("WorkingDir");
+        }
+        if self.get_command_line_arguments().is_some() {
+            sections.push("CommandLineArguments");
+        }
+        if self.get_icon_location().is_some() {
+            sections.push("IconLocation");
+        }
+        if self.get_extra_data().is_some() {
+            sections.push("ExtraData");
+        }
+
+        let mut zeroed_header_times = vec![];
+        if file_time(&header.ctime).is_none() {
+            zeroed_header_times.push("ctime");
+        }
+        if file_time(&header.atime).is_none() {
+            zeroed_header_times.push("atime");
+        }
+        if file_time(&header.mtime).is_none() {
+            zeroed_header_times.push("mtime");
+        }
+
+        let id_list_item_types = match self.get_link_target_id_list() {
+            Some(id_list) => id_list
+                .items()
+                .map(|item| format!("0x{:02X}", item.class_type))
+                .collect(),
+            None => vec![],
+        };
+
+        let extra_data = self.get_extra_data().as_ref();
+        let extra_data_blocks = match extra_data {
+            Some(extra_data) => extra_data
+                .blocks
+                .iter()
+                .map(|block| format!("{}:{}", block.name(), block.size))
+                .collect(),
+            None => vec![],
+        };
+
+        FingerprintFeatures {
+            link_flags: header.flags.names().iter().map(|f| f.to_string()).collect(),
+            file_attributes: header.file_attr.iter().map(|a| a.to_string()).collect(),
+            sections,
+            zeroed_header_times,
+            reserved0: header.reserved0,
+            reserved1: header.reserved1,
+            reserved2: header.reserved2,
+            show_command: header.show_command.as_u32(),
+            icon_index: header.icon_index_signed(),
+            hot_key: header.hot_key.to_string(),
+            id_list_item_types,
+            link_info_header_size: self.get_link_info().as_ref().map(|l| l.header_size),
+            extra_data_blocks,
+            machine_id: extra_data
+                .and_then(|extra_data| extra_data.tracker())
+                .map(|tracker| tracker.machine_id.to_owned()),
+            drive_serial_number: self
+                .get_link_info()
+                .as_ref()
+                .and_then(|link_info| link_info.volume_id.as_ref())
+                .map(|volume_id| volume_id.get_serial_number().to_owned()),
+        }
+    }
+}
@@ -0,0 +1,430 @@
+//! Indicators of compromise (URLs, IPs, domains, file paths, registry paths, etc) extracted from strings.
+
+use crate::LNKParser;
+use chrono::{DateTime, SecondsFormat, Utc};
+use regex::Regex;
+use serde::Serialize;
+use serde_json::{json, Value};
+use sha1::{Digest, Sha1};
+use std::net::IpAddr;
+use std::sync::LazyLock;
+
+static URL_REGEX: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r#"(?i)\b(?:https?|ftp)://[^\s"'<>|^`(){}\[\]]+"#).unwrap());
+static IP_REGEX: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(
+        r"\b(?:(?:25[0-5]|2[0-4]\d|1\d\d|[1-9]?\d)\.){3}(?:25[0-5]|2[0-4]\d|1\d\d|[1-9]?\d)\b",
+    )
+    .unwrap()
+});
+static DOMAIN_REGEX: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(r"(?i)\b(?:[a-z0-9](?:[a-z0-9-]{0,61}[a-z0-9])?\.)+[a-z]{2,63}\b").unwrap()
+});
+static UNC_HOST_REGEX: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"(?i)(?:^|[^\\])\\\\([a-z0-9._$@-]+)\\").unwrap());
+static EMAIL_REGEX: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"(?i)\b[a-z0-9._%+-]+@(?:[a-z0-9-]+\.)+[a-z]{2,63}\b").unwrap());
+static FILE_PATH_REGEX: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(r#"(?i)(?:\b[a-z]:\\|\\\\[a-z0-9._$-]+\\|%[a-z0-9_()]+%\\)[^\s"'<>|*?;,&]*"#)
+        .unwrap()
+});
+static FILE_NAME_REGEX: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(&format!(
+        r#"(?i)[^\s\\/:*?"'<>|%;,&()=]+\.(?:{})\b"#,
+        FILE_EXTENSIONS.join("|")
+    ))
+    .unwrap()
+});
+static REGISTRY_PATH_REGEX: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(r#"(?i)\b(?:HKLM|HKCU|HKCR|HKU|HKCC|HKEY_LOCAL_MACHINE|HKEY_CURRENT_USER|HKEY_CLASSES_ROOT|HKEY_USERS|HKEY_CURRENT_CONFIG):?\\[^\s"'<>|;,&]*"#)
+        .unwrap()
+});
+
+/// Generic top level domains accepted in addition to the two letter country codes.
+const GENERIC_TLDS: &[&str] = &[
+    "com", "net", "org", "info", "biz", "edu", "gov", "mil", "int", "xyz", "top", "online", "site",
+    "club", "shop", "store", "live", "app", "dev", "cloud", "tech", "space", "website", "icu",
+    "work", "pro", "name", "mobi", "asia", "link", "click", "download", "win", "bid", "loan",
+    "stream", "review", "trade", "party", "science", "host", "fun", "monster", "buzz", "cyou",
+    "rest", "sbs", "life", "world", "today", "email", "services", "support", "digital", "onion",
+    "local",
+];
+
+/// .NET namespaces that look like domains in PowerShell code (e.g. `System.Net.WebClient`).
+const DOTNET_NAMESPACES: &[&str] = &["system", "microsoft", "net", "io", "text", "management"];
+
+/// Extensions that look like top level domains but are file names (e.g. `cmd.exe`).
+const FILE_EXTENSIONS: &[&str] = &[
+    "exe", "dll", "sys", "lnk", "bat", "cmd", "ps1", "psm1", "psd1", "vbs", "vbe", "js", "jse",
+    "wsf", "wsh", "hta", "msi", "scr", "cpl", "txt", "log", "ini", "dat", "tmp", "zip", "rar",
+    "doc", "docx", "xls", "xlsx", "ppt", "pptx", "pdf", "rtf", "jpg", "jpeg", "png", "gif", "ico",
+    "bmp", "iso", "img", "vhd", "xml", "json", "csv", "db", "jar", "py", "sh", "url",
+];
+
+/// The serialized fields that are not part of the LNK file data.
+const SKIPPED_FIELDS: &[&str] = &["lnk_file_metadata", "hashes"];
+
+/// The STIX 2.1 namespace used to generate deterministic identifiers.
+const STIX_NAMESPACE: [u8; 16] = [
+    0x00, 0xab, 0xed, 0xb4, 0xaa, 0x42, 0x46, 0x6c, 0x9c, 0x01, 0xfe, 0xd2, 0x33, 0x15, 0xa9, 0xb7,
+];
+
+/// The type of an IOC.
+#[derive(Debug, Serialize, Clone, Copy, PartialEq, Eq, Hash)]
+#[serde(rename_all = "snake_case")]
+pub enum IocType {
+    Url,
+    Ip,
+    Domain,
+    UncHost,
+    Email,
+    FilePath,
+    FileName,
+    RegistryPath,
+}
+
+impl IocType {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            IocType::Url => "url",
+            IocType::Ip => "ip",
+            IocType::Domain => "domain",
+            IocType::UncHost => "unc_host",
+            IocType::Email => "email",
+            IocType::FilePath => "file_path",
+            IocType::FileName => "file_name",
+            IocType::RegistryPath => "registry_path",
+        }
+    }
+}
+
+/// The IOCs found in one or more strings, each list is deduplicated and sorted by first appearance.
+#[derive(Debug, Serialize, Clone, Default, PartialEq, Eq)]
+pub struct Iocs {
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub urls: Vec<String>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub ips: Vec<String>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub domains: Vec<String>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub unc_hosts: Vec<String>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub emails: Vec<String>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub file_paths: Vec<String>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub file_names: Vec<String>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub registry_paths: Vec<String>,
+}
+
+impl Iocs {
+    /// Extract the IOCs from a string.
+    pub fn extract_from(s: &str) -> Self {
+        let mut iocs = Self::default();
+        iocs.extract(s);
+        iocs
+    }
+
+    /// Extract the IOCs from a string and add them to the existing ones.
+    pub fn extract(&mut self, s: &str) {
+        for url in URL_REGEX.find_iter(s) {
+            let url = url.as_str().trim_end_matches(['.', ',', ';']);
+            push_unique(&mut self.urls, url);
+        }
+        for ip in IP_REGEX.find_iter(s) {
+            push_unique(&mut self.ips, ip.as_str());
+        }
+        for domain in DOMAIN_REGEX.find_iter(s) {
+            let domain = domain.as_str().to_lowercase();
+            let tld = domain.rsplit('.').next().unwrap_or_default();
+            let first_label = domain.split('.').next().unwrap_or_default();
+            if FILE_EXTENSIONS.contains(&tld)
+                || !(tld.len() == 2 || GENERIC_TLDS.contains(&tld))
+                || DOTNET_NAMESPACES.contains(&first_label)
+            {
+                continue;
+            }
+            push_unique(&mut self.domains, &domain);
+        }
+        for captures in UNC_HOST_REGEX.captures_iter(s) {
+            push_unique(&mut self.unc_hosts, &captures[1]);
+        }
+        for email in EMAIL_REGEX.find_iter(s) {
+            push_unique(&mut self.emails, email.as_str());
+        }
+        for file_path in FILE_PATH_REGEX.find_iter(s) {
+            let file_path = file_path.as_str().trim_end_matches(['.', ')']);
+            push_unique(&mut self.file_paths, file_path);
+        }
+        for file_name in FILE_NAME_REGEX.find_iter(s) {
+            // ignore the domains of email addresses and URLs
+            let file_name = file_name.as_str();
+            if !file_name.contains('@') {
+                push_unique(&mut self.file_names, file_name);
+            }
+        }
+        for registry_path in REGISTRY_PATH_REGEX.find_iter(s) {
+            push_unique(&mut self.registry_paths, registry_path.as_str());
+        }
+    }
+
+    /// Add the IOCs of `other` that are not already present.
+    pub fn merge(&mut self, other: Iocs) {
+        for (ioc_type, value) in other.items() {
+            push_unique(self.values_mut(ioc_type), value);
+        }
+    }
+
+    /// Returns all the IOCs with their types.
+    pub fn items(&self) -> Vec<(IocType, &str)> {
+        let lists = [
+            (IocType::Url, &self.urls),
+            (IocType::Ip, &self.ips),
+            (IocType::Domain, &self.domains),
+            (IocType::UncHost, &self.unc_hosts),
+            (IocType::Email, &self.emails),
+            (IocType::FilePath, &self.file_paths),
+            (IocType::FileName, &self.file_names),
+            (IocType::RegistryPath, &self.registry_paths),
+        ];
+        lists
+            .iter()
+            .flat_map(|(ioc_type, values)| values.iter().map(move |v| (*ioc_type, v.as_str())))
+            .collect()
+    }
+
+    fn values_mut(&mut self, ioc_type: IocType) -> &mut Vec<String> {
+        match ioc_type {
+            IocType::Url => &mut self.urls,
+            IocType::Ip => &mut self.ips,
+            IocType::Domain => &mut self.domains,
+            IocType::UncHost => &mut self.unc_hosts,
+            IocType::Email => &mut self.emails,
+            IocType::FilePath => &mut self.file_paths,
+            IocType::FileName => &mut self.file_names,
+            IocType::RegistryPath => &mut self.registry_paths,
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.urls.is_empty()
+            && self.ips.is_empty()
+            && self.domains.is_empty()
+            && self.unc_hosts.is_empty()
+            && self.emails.is_empty()
+            && self.file_paths.is_empty()
+            && self.file_names.is_empty()
+            && self.registry_paths.is_empty()
+    }
+}
+
+/// An IOC tagged with the field it was extracted from.
+#[derive(Debug, Serialize, Clone, PartialEq, Eq)]
+pub struct TaggedIoc {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub lnk_full_path: Option<String>,
+    #[serde(rename = "type")]
+    pub ioc_type: IocType,
+    pub value: String,
+    /// The path of the field in the serialized `LNKParser` (e.g. `link_info.local_base_path`)
+    /// or `extra_data.{block name}` for the strings of the ExtraData blocks that are not implemented.
+    pub source: String,
+}
+
+impl TaggedIoc {
+    /// The STIX 2.1 pattern matching the IOC.
+    pub fn stix_pattern(&self) -> String {
+        let value = stix_escape(&self.value);
+        match self.ioc_type {
+            IocType::Url => format!("[url:value = '{}']", value),
+            IocType::Ip => format!("[ipv4-addr:value = '{}']", value),
+            // the host of a UNC path could be an IP address
+            IocType::Domain | IocType::UncHost => match self.value.parse::<IpAddr>() {
+                Ok(IpAddr::V4(_)) => format!("[ipv4-addr:value = '{}']", value),
+                Ok(IpAddr::V6(_)) => format!("[ipv6-addr:value = '{}']", value),
+                Err(_) => format!("[domain-name:value = '{}']", value),
+            },
+            IocType::Email => format!("[email-addr:value = '{}']", value),
+            IocType::FileName => format!("[file:name = '{}']", value),
+            IocType::FilePath => match self.value.rsplit_once('\\') {
+                Some((directory, name)) if !name.is_empty() => format!(
+                    "[file:parent_directory_ref.path = '{}' AND file:name = '{}']",
+                    stix_escape(directory),
+                    stix_escape(name)
+                ),
+                _ => format!("[directory:path = '{}']", value),
+            },
+            IocType::RegistryPath => format!("[windows-registry-key:key = '{}']", value),
+        }
+    }
+}
+
+impl LNKParser {
+    /// Extract the IOCs from every string of the parsed file, each IOC is tagged with the field it came from.
+    pub fn iocs(&self) -> Vec<TaggedIoc> {
+        let lnk_full_path = self
+            .get_lnk_file_metadata()
+            .as_ref()
+            .map(|metadata| metadata.get_full_path().to_owned());
+        let mut strings = vec![];
+        if let Ok(value) = serde_json::to_value(self) {
+            collect_strings(&value, String::new(), &mut strings);
+        }
+        #[cfg(feature = "deobfuscation")]
+        if let Some(decoded) = self.deobfuscated_arguments() {
+            for (index, layer) in decoded.layers.into_iter().enumerate() {
+                strings.push((
+                    format!("decoded_arguments.layers[{}].value", index),
+                    layer.value,
+                ));
+            }
+        }
+        if let Some(extra_data) = self.get_extra_data() {
+            for block in &extra_data.blocks {
+                for s in &block.strings {
+                    strings.push((format!("extra_data.{}", block.name()), s.as_str().into()));
+                }
+            }
+        }
+
+        let mut iocs: Vec<TaggedIoc> = vec![];
+        for (source, s) in strings {
+            for (ioc_type, value) in Iocs::extract_from(&s).items() {
+                let exists = iocs.iter().any(|ioc| {
+                    ioc.ioc_type == ioc_type && ioc.value == value && ioc.source == source
+                });
+                if !exists {
+                    iocs.push(TaggedIoc {
+                        lnk_full_path: lnk_full_path.to_owned(),
+                        ioc_type,
+                        value: value.to_string(),
+                        source: source.to_owned(),
+                    });
+                }
+            }
+        }
+        iocs
+    }
+}
+
+/// Build a STIX 2.1 bundle with an indicator for every unique IOC (the fields and the LNK files it was
+/// found in are listed in `x_lnk_source_fields` and `x_lnk_full_paths`). The identifiers are deterministic
+/// so importing the same IOCs twice does not create duplicates.
+pub fn to_stix_bundle(iocs: &[TaggedIoc], created: DateTime<Utc>) -> Value {
+    let created = created.to_rfc3339_opts(SecondsFormat::Millis, true);
+    // (pattern, ioc, source fields, LNK files) in the order of first appearance
+    let mut indicators: Vec<(String, &TaggedIoc, Vec<&str>, Vec<&str>)> = vec![];
+    for ioc in iocs {
+        let pattern = ioc.stix_pattern();
+        let index = match indicators.iter().position(|(p, ..)| *p == pattern) {
+            Some(index) => index,
+            None => {
+                indicators.push((pattern, ioc, vec![], vec![]));
+                indicators.len() - 1
+            }
+        };
+        let (_, _, sources, lnk_full_paths) = &mut indicators[index];
+        if !sources.contains(&ioc.source.as_str()) {
+            sources.push(&ioc.source);
+        }
+        if let Some(lnk_full_path) = &ioc.lnk_full_path {
+            if !lnk_full_paths.contains(&lnk_full_path.as_str()) {
+                lnk_full_paths.push(lnk_full_path);
+            }
+        }
+    }
+
+    let objects = indicators
+        .into_iter()
+        .map(|(pattern, ioc, sources, lnk_full_paths)| {
+            json!({
+                "type": "indicator",
+                "spec_version": "2.1",
+                "id": format!("indicator--{}", uuid_v5(&pattern)),
+                "created": created,
+                "modified": created,
+                "name": ioc.value,
+                "description": format!("{} extracted from LNK file(s)", ioc.ioc_type.as_str()),
+                "indicator_types": ["unknown"],
+                "pattern": pattern,
+                "pattern_type": "stix",
+                "valid_from": created,
+                "x_ioc_type": ioc.ioc_type,
+                "x_lnk_source_fields": sources,
+                "x_lnk_full_paths": lnk_full_paths,
+            })
+        })
+        .collect::<Vec<Value>>();
+    let bundle_name = objects
+        .iter()
+        .map(|object| object["id"].as_str().unwrap_or_default())
+        .collect::<Vec<&str>>()
+        .join(",");
+    json!({
+        "type": "bundle",
+        "id": format!("bundle--{}", uuid_v5(&bundle_name)),
+        "objects": objects,
+    })
+}
+
+/// Collect the string values with their paths (arrays are indexed as `name[index]`).
+fn collect_strings(value: &Value, path: String, strings: &mut Vec<(String, String)>) {
+    if SKIPPED_FIELDS.contains(&path.as_str()) {
+        return;
+    }
+    match value {
+        Value::String(s) => strings.push((path, s.to_owned())),
+        Value::Array(items) => {
+            for (index, item) in items.iter().enumerate() {
+                collect_strings(item, format!("{}[{}]", path, index), strings);
+            }
+        }
+        Value::Object(map) => {
+            for (key, item) in map {
+                let path = if path.is_empty() {
+                    key.to_owned()
+                } else {
+                    format!("{}.{}", path, key)
+                };
+                collect_strings(item, path, strings);
+            }
+        }
+        _ => {}
+    }
+}
+
+fn stix_escape(value: &str) -> String {
+    value.replace('\\', "\\\\").replace('\'', "\\'")
+}
+
+/// Name based UUID (version 5) in the STIX namespace.
+fn uuid_v5(name: &str) -> String {
+    let mut hasher = Sha1::new();
+    hasher.update(STIX_NAMESPACE);
+    hasher.update(name.as_bytes());
+    let hash = hasher.finalize();
+    let mut bytes = [0u8; 16];
+    bytes.copy_from_slice(&hash[..16]);
+    bytes[6] = (bytes[6] & 0x0F) | 0x50;
+    bytes[8] = (bytes[8] & 0x3F) | 0x80;
+    let hex = bytes
+        .iter()
+        .map(|b| format!("{:02x}", b))
+        .collect::<String>();
+    format!(
+        "{}-{}-{}-{}-{}",
+        &hex[0..8],
+        &hex[8..12],
+        &hex[12..16],
+        &hex[16..20],
+        &hex[20..32]
+    )
+}
+
+fn push_unique(values: &mut Vec<String>, value: &str) {
+    if !value.is_empty() && !values.iter().any(|v| v == value) {
+        values.push(value.to_string());
+    }
+}
@@ -0,0 +1,460 @@
+//! The location of every structure and field in the parsed data with its decoded value, used to
+//! dump the raw bytes annotated like a 010 Editor template.
+
+use crate::extra_data::ExtraDataTypes;
+use crate::LNKParser;
+use serde::Serialize;
+use winparsingtools::structs::shell_items::Name;
+
+/// The size of the `ShellLinkHeader` structure.
+const HEADER_SIZE: u64 = 0x4C;
+
+/// The fields of the `ShellLinkHeader` structure and their sizes.
+const HEADER_FIELDS: [(&str, u64); 14] = [
+    ("HeaderSize", 4),
+    ("LinkCLSID", 16),
+    ("LinkFlags", 4),
+    ("FileAttributes", 4),
+    ("CreationTime", 8),
+    ("AccessTime", 8),
+    ("WriteTime", 8),
+    ("FileSize", 4),
+    ("IconIndex", 4),
+    ("ShowCommand", 4),
+    ("HotKey", 2),
+    ("Reserved1", 2),
+    ("Reserved2", 4),
+    ("Reserved3", 4),
+];
+
+/// A range of the parsed data. The structures have their fields in `children` and an empty value.
+#[derive(Debug, Serialize, Clone, PartialEq, Eq)]
+pub struct Region {
+    /// The absolute offset in the parsed data.
+    pub offset: u64,
+    pub size: u64,
+    pub name: String,
+    /// The decoded value.
+    pub value: String,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub children: Vec<Region>,
+}
+
+impl Region {
+    fn new(offset: u64, size: u64, name: &str, value: String) -> Self {
+        Self {
+            offset,
+            size,
+            name: name.to_string(),
+            value,
+            children: vec![],
+        }
+    }
+
+    fn with_children(offset: u64, size: u64, name: &str, children: Vec<Region>) -> Self {
+        Self {
+            offset,
+            size,
+            name: name.to_string(),
+            value: String::new(),
+            children,
+        }
+    }
+
+    /// The bytes of the region, `data` is the parsed data. Truncated if the data is shorter than the region.
+    pub fn bytes<'a>(&self, data: &'a [u8]) -> &'a [u8] {
+        let start = (self.offset as usize).min(data.len());
+        let end = (self.offset.saturating_add(self.size) as usize).min(data.len());
+        &data[start..end]
+    }
+}
+
+/// Lays out consecutive fields starting at `offset`.
+struct Fields {
+    offset: u64,
+    regions: Vec<Region>,
+}
+
+impl Fields {
+    fn new(offset: u64) -> Self {
+        Self {
+            offset,
+            regions: vec![],
+        }
+    }
+
+    fn push<V: ToString>(&mut self, name: &str, size: u64, value: V) {
+        self.regions
+            .push(Region::new(self.offset, size, name, value.to_string()));
+        self.offset += size;
+    }
+}
+
+impl LNKParser {
+    /// Returns the regions of the structures in the order they appear in the data, the data after
+    /// the ExtraData terminal block (or after the last parsed structure) is an `Overlay` region.
+    pub fn layout(&self) -> Vec<Region> {
+        let mut regions = vec![self.header_region()];
+        if let Some(link_target_id_list) = &self.link_target_id_list {
+            let offset = link_target_id_list.offset;
+            let size = link_target_id_list.size as u64;
+            let mut children = vec![Region::new(offset, 2, "IDListSize", size.to_string())];
+            for (index, entry) in link_target_id_list.entries().into_iter().enumerate() {
+                let item_type = entry
+                    .item
+                    .shell_item_data
+                    .as_ref()
+                    .and_then(|data| serde_json::to_value(data).ok())
+                    .map(|value| match value {
+                        serde_json::Value::Object(map) => map.keys().cloned().collect(),
+                        _ => String::new(),
+                    })
+                    .unwrap_or_else(|| format!("0x{:02X}", entry.item.class_type));
+                let mut fields = Fields::new(entry.offset);
+                fields.push("ItemIDSize", 2, entry.size);
+                fields.push(
+                    "Data",
+                    (entry.size as u64).saturating_sub(2),
+                    json_string(&entry.item.shell_item_data),
+                );
+                let mut item = Region::with_children(
+                    entry.offset,
+                    entry.size as u64,
+                    &format!("ShellItem[{}]", index),
+                    fields.regions,
+                );
+                item.value = format!("{} '{}'", item_type, entry.item.name());
+                children.push(item);
+            }
+            // the IDList ends with a zero TerminalID
+            children.push(Region::new(offset + size, 2, "TerminalID", "0".to_string()));
+            regions.push(Region::with_children(
+                offset,
+                size + 2,
+                "LinkTargetIDList",
+                children,
+            ));
+        }
+        if let Some(link_info) = &self.link_info {
+            regions.push(self.link_info_region(link_info));
+        }
+
+        let string_data = [
+            ("NameString", self.offsets.name_string, &self.name_string),
+            (
+                "RelativePath",
+                self.offsets.relative_path,
+                &self.relative_path,
+            ),
+            ("WorkingDir", self.offsets.working_dir, &self.working_dir),
+            (
+                "CommandLineArguments",
+                self.offsets.command_line_arguments,
+                &self.command_line_arguments,
+            ),
+            (
+                "IconLocation",
+                self.offsets.icon_location,
+                &self.icon_location,
+            ),
+        ];
+        let string_data = string_data
+            .iter()
+            .filter_map(|(name, offset, value)| Some((*name, (*offset)?, value.as_ref()?)))
+            .collect::<Vec<_>>();
+        for (i, (name, offset, value)) in string_data.iter().enumerate() {
+            // the strings are consecutive, a string ends where the next structure starts
+            let end = string_data
+                .get(i + 1)
+                .map(|(_, offset, _)| *offset)
+                .unwrap_or(self.offsets.extra_data);
+            let mut fields = Fields::new(*offset);
+            let value = value.to_string();
+            fields.push("CountCharacters", 2, value.encode_utf16().count());
+            fields.push("String", end.saturating_sub(*offset + 2), value);
+            regions.push(Region::with_children(
+                *offset,
+                end.saturating_sub(*offset),
+                name,
+                fields.regions,
+            ));
+        }
+
+        if let Some(extra_data) = &self.extra_data {
+            let mut children = vec![];
+            let mut trackers = extra_data.extra_data_blocks.iter();
+            for block in &extra_data.blocks {
+                let mut fields = Fields::new(block.offset);
+                fields.push("BlockSize", 4, block.size);
+                fields.push("BlockSignature", 4, format!("0x{:08X}", block.signature));
+                match block.signature {
+                    0xA0000003 => match trackers.next() {
+                        Some(ExtraDataTypes::Tracker(tracker)) => {
+                            fields.push("Length", 4, tracker.size);
+                            fields.push("Version", 4, tracker.version);
+                            fields.push("MachineID", 16, &tracker.machine_id);
+                            fields.push("VolumeDroid", 16, &tracker.volume_droid);
+                            fields.push("FileDroid", 16, &tracker.file_droid);
+                            fields.push("VolumeDroidBirth", 16, &tracker.volume_droid_birth);
+                            fields.push("FileDroidBirth", 16, &tracker.file_droid_birth);
+                        }
+                        None => fields.push("Data", (block.size as u64).saturating_sub(8), ""),
+                    },
+                    _ => fields.push(
+                        "Data",
+                        (block.size as u64).saturating_sub(8),
+                        block.strings.join(", "),
+                    ),
+                }
+                children.push(Region::with_children(
+                    block.offset,
+                    block.size as u64,
+                    block.name(),
+                    fields.regions,
+                ));
+            }
+            let terminal_offset = self.parsed_size.saturating_sub(4);
+            children.push(Region::new(
+                terminal_offset,
+                4,
+                "TerminalBlock",
+                "0".to_string(),
+            ));
+            regions.push(Region::with_children(
+                self.offsets.extra_data,
+                self.parsed_size.saturating_sub(self.offsets.extra_data),
+                "ExtraData",
+                children,
+            ));
+        }
+
+        if self.overlay_size() > 0 {
+            regions.push(Region::new(
+                self.parsed_size,
+                self.overlay_size(),
+                "Overlay",
+                format!("{} bytes after the parsed structures", self.overlay_size()),
+            ));
+        }
+        regions
+    }
+
+    fn header_region(&self) -> Region {
+        let header = &self.shell_link_header;
+        let values = [
+            header.header_size.to_string(),
+            json_string(&header.guid),
+            header.flags.to_string(),
+            header
+                .file_attr
+                .iter()
+                .map(|attr| format!("{:?}", attr))
+                .collect::<Vec<String>>()
+                .join(","),
+            json_string(&header.ctime),
+            json_string(&header.atime),
+            json_string(&header.mtime),
+            header.file_size.to_string(),
+            header.icon_index_signed().to_string(),
+            header.show_command.to_string(),
+            header.hot_key.to_string(),
+            header.reserved0.to_string(),
+            header.reserved1.to_string(),
+            header.reserved2.to_string(),
+        ];
+        let mut fields = Fields::new(self.offsets.shell_link_header);
+        for ((name, size), value) in HEADER_FIELDS.iter().zip(values.iter()) {
+            fields.push(name, *size, value);
+        }
+        Region::with_children(
+            self.offsets.shell_link_header,
+            HEADER_SIZE,
+            "ShellLinkHeader",
+            fields.regions,
+        )
+    }
+
+    fn link_info_region(&self, link_info: &crate::link_info::LinkInfo) -> Region {
+        let offset = link_info.offset;
+        let mut fields = Fields::new(offset);
+        fields.push("LinkInfoSize", 4, link_info.size);
+        fields.push("LinkInfoHeaderSize", 4, link_info.header_size);
+        fields.push("LinkInfoFlags", 4, &link_info.flags);
+        fields.push("VolumeIDOffset", 4, link_info.volume_id_offset);
+        fields.push("LocalBasePathOffset", 4, link_info.local_base_path_offset);
+        fields.push(
+            "CommonNetworkRelativeLinkOffset",
+            4,
+            link_info.common_network_relative_link_offset,
+        );
+        fields.push(
+            "CommonPathSuffixOffset",
+            4,
+            link_info.common_path_suffix_offset,
+        );
+        if let Some(local_base_path_offset_unicode) = link_info.local_base_path_offset_unicode {
+            fields.push(
+                "LocalBasePathOffsetUnicode",
+                4,
+                local_base_path_offset_unicode,
+            );
+        }
+        if let Some(common_path_suffix_offset_unicode) = link_info.common_path_suffix_offset_unicode
+        {
+            fields.push(
+                "CommonPathSuffixOffsetUnicode",
+                4,
+                common_path_suffix_offset_unicode,
+            );
+        }
+        let mut children = fields.regions;
+
+        if let Some(volume_id) = &link_info.volume_id {
+            let volume_id_offset = offset + link_info.volume_id_offset as u64;
+            let mut fields = Fields::new(volume_id_offset);
+            fields.push("VolumeIDSize", 4, volume_id.get_size());
+            fields.push("DriveType", 4, json_string(volume_id.get_drive_type()));
+            fields.push("DriveSerialNumber", 4, volume_id.get_serial_number());
+            fields.push("VolumeLabelOffset", 4, volume_id.get_volume_label_offset());
+            let label = match volume_id.get_volume_label_offset_unicode() {
+                Some(label_offset) => {
+                    fields.push("VolumeLabelOffsetUnicode", 4, label_offset);
+                    (*label_offset, true)
+                }
+                None => (*volume_id.get_volume_label_offset(), false),
+            };
+            let mut regions = fields.regions;
+            if let Some(volume_label) = volume_id.get_volume_label() {
+                regions.push(string_region(
+                    volume_id_offset + label.0 as u64,
+                    if label.1 {
+                        "VolumeLabelUnicode"
+                    } else {
+                        "VolumeLabel"
+                    },
+                    volume_label,
+                    label.1,
+                ));
+            }
+            children.push(Region::with_children(
+                volume_id_offset,
+                *volume_id.get_size() as u64,
+                "VolumeID",
+                regions,
+            ));
+        }
+        if let Some(local_base_path) = &link_info.local_base_path {
+            let unicode = link_info.local_base_path_offset_unicode.is_some();
+            let string_offset = link_info
+                .local_base_path_offset_unicode
+                .unwrap_or(link_info.local_base_path_offset);
+            children.push(string_region(
+                offset + string_offset as u64,
+                if unicode {
+                    "LocalBasePathUnicode"
+                } else {
+                    "LocalBasePath"
+                },
+                local_base_path,
+                unicode,
+            ));
+        }
+        if let Some(link) = &link_info.common_network_relative_link {
+            let link_offset = offset + link_info.common_network_relative_link_offset as u64;
+            let mut fields = Fields::new(link_offset);
+            fields.push("CommonNetworkRelativeLinkSize", 4, link.size);
+            let flags = link
+                .flags
+                .iter()
+                .map(|flag| format!("{:?}", flag))
+                .collect::<Vec<String>>();
+            fields.push("CommonNetworkRelativeLinkFlags", 4, flags.join(","));
+            fields.push("NetNameOffset", 4, link.net_name_offset);
+            fields.push("DeviceNameOffset", 4, link.device_name_offset);
+            fields.push(
+                "NetworkProviderType",
+                4,
+                json_string(&link.network_provider_type),
+            );
+            if let Some(net_name_offset_unicode) = link.net_name_offset_unicode {
+                fields.push("NetNameOffsetUnicode", 4, net_name_offset_unicode);
+            }
+            if let Some(device_name_offset_unicode) = link.device_name_offset_unicode {
+                fields.push("DeviceNameOffsetUnicode", 4, device_name_offset_unicode);
+            }
+            let mut regions = fields.regions;
+            let names = [
+                (
+                    "NetName",
+                    &link.net_name,
+                    link.net_name_offset,
+                    link.net_name_offset_unicode,
+                ),
+                (
+                    "DeviceName",
+                    &link.device_name,
+                    link.device_name_offset,
+                    link.device_name_offset_unicode,
+                ),
+            ];
+            for (name, value, name_offset, name_offset_unicode) in names.iter() {
+                if let Some(value) = value {
+                    let unicode = name_offset_unicode.is_some();
+                    regions.push(string_region(
+                        link_offset + name_offset_unicode.unwrap_or(*name_offset) as u64,
+                        &if unicode {
+                            format!("{}Unicode", name)
+                        } else {
+                            name.to_string()
+                        },
+                        value,
+                        unicode,
+                    ));
+                }
+            }
+            children.push(Region::with_children(
+                link_offset,
+                link.size as u64,
+                "CommonNetworkRelativeLink",
+                regions,
+            ));
+        }
+        if let Some(common_path_suffix) = &link_info.common_path_suffix {
+            let unicode = link_info.common_path_suffix_offset_unicode.is_some();
+            let string_offset = link_info
+                .common_path_suffix_offset_unicode
+                .unwrap_or(link_info.common_path_suffix_offset);
+            children.push(string_region(
+                offset + string_offset as u64,
+                if unicode {
+                    "CommonPathSuffixUnicode"
+                } else {
+                    "CommonPathSuffix"
+                },
+                common_path_suffix,
+                unicode,
+            ));
+        }
+        Region::with_children(offset, link_info.size as u64, "LinkInfo", children)
+    }
+}
+
+/// A NULL-terminated string, `unicode` strings are UTF-16LE and the others are single byte strings.
+fn string_region(offset: u64, name: &str, value: &str, unicode: bool) -> Region {
+    let size = if unicode {
+        (value.encode_utf16().count() as u64 + 1) * 2
+    } else {
+        value.chars().count() as u64 + 1
+    };
+    Region::new(offset, size, name, value.to_string())
+}
+
+/// The serialized value, without quotes for strings.
+fn json_string<T: Serialize>(value: &T) -> String {
+    match serde_json::to_value(value) {
+        Ok(serde_json::Value::String(s)) => s,
+        Ok(serde_json::Value::Null) => String::new(),
+        Ok(value) => value.to_string(),
+        Err(_) => String::new(),
+    }
+}
@@ -1,15 +1,37 @@
 #![allow(non_snake_case)]
 #![allow(non_camel_case_types)]
+pub mod analysis;
+#[cfg(feature = "deobfuscation")]
+pub mod deobfuscation;
+pub mod diff;
 pub mod extra_data;
+pub mod fingerprint;
+#[cfg(feature = "iocs")]
+pub mod iocs;
+pub mod layout;
 pub mod link_info;
-mod link_target_id_list;
+pub mod link_target_id_list;
+pub mod normalized_record;
+#[cfg(feature = "parquet")]
+pub mod parquet;
+#[cfg(feature = "rules")]
+pub mod rules;
 pub mod shell_link_header;
+#[cfg(feature = "sqlite")]
+pub mod sqlite;
+pub mod timeline;
+pub mod timestamps;
 
-use extra_data::{ExtraData, ExtraDataTypes};
+use extra_data::ExtraData;
 use getset::Getters;
 use link_info::LinkInfo;
 use link_target_id_list::LinkTargetIDList;
-use serde::{ser::SerializeStruct, Serialize, Serializer};
+use md5::Md5;
+pub use normalized_record::NormalizedRecord;
+use serde::{Serialize, Serializer};
+use sha1::Sha1;
+use sha2::{Digest, Sha256};
+pub use shell_link_header::is_lnk;
 use shell_link_header::{LinkFlags, ShellLinkHeader};
 
 use chrono::{DateTime, Utc};
@@ -17,7 +39,6 @@ use std::{
     collections::HashMap,
     fs,
     io::{Cursor, Read, Seek, SeekFrom},
-    time::SystemTime,
 };
 use winparsingtools::{
     structs::StringData,
@@ -25,13 +46,33 @@ use winparsingtools::{
     ReaderError,
 };
 
-#[derive(Debug, Getters, Clone)]
+/// File system metadata of the LNK file itself. Values that are not available on the current platform are `None`.
+#[derive(Debug, Getters, Clone, Serialize)]
 #[getset(get = "pub with_prefix")]
 pub struct LnkFileMetaData {
     full_path: String,
-    mtime: DateTime<Utc>,
-    atime: DateTime<Utc>,
-    ctime: DateTime<Utc>,
+    size: u64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    modified: Option<DateTime<Utc>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    accessed: Option<DateTime<Utc>>,
+    /// The inode change time (Unix only).
+    #[serde(skip_serializing_if = "Option::is_none")]
+    changed: Option<DateTime<Utc>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    born: Option<DateTime<Utc>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    inode: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    uid: Option<u32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    gid: Option<u32>,
+    /// The file type and permission bits (`st_mode`), serialized in octal.
+    #[serde(
+        skip_serializing_if = "Option::is_none",
+        serialize_with = "serialize_mode"
+    )]
+    mode: Option<u32>,
 }
 
 impl LnkFileMetaData {
@@ -41,59 +82,86 @@ impl LnkFileMetaData {
             Ok(path_buf) => path_buf
                 .to_str()
                 .ok_or_else(|| {
-                    std::io::Error::new(
-                        std::io::ErrorKind::Other,
-                        format!("Can not Read full_path for '{}'", path),
-                    )
+                    std::io::Error::other(format!("Can not Read full_path for '{}'", path))
                 })?
                 .to_string()
                 .replace("\\\\?\\", ""),
             Err(_) => path.to_string(),
         };
-        let mtime: DateTime<Utc> = DateTime::from(match file_metadata.created() {
-            Ok(date) => date,
-            Err(_) => SystemTime::UNIX_EPOCH,
-        });
-        let atime: DateTime<Utc> = DateTime::from(match file_metadata.accessed() {
-            Ok(date) => date,
-            Err(_) => SystemTime::UNIX_EPOCH,
-        });
-        let ctime: DateTime<Utc> = DateTime::from(match file_metadata.modified() {
-            Ok(date) => date,
-            Err(_) => SystemTime::UNIX_EPOCH,
-        });
-        Ok(Self {
+
+        #[cfg_attr(not(unix), allow(unused_mut))]
+        let mut lnk_file_metadata = Self {
             full_path,
-            mtime,
-            ctime,
-            atime,
-        })
+            size: file_metadata.len(),
+            modified: file_metadata.modified().ok().map(DateTime::from),
+            accessed: file_metadata.accessed().ok().map(DateTime::from),
+            changed: None,
+            born: file_metadata.created().ok().map(DateTime::from),
+            inode: None,
+            uid: None,
+            gid: None,
+            mode: None,
+        };
+
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::MetadataExt;
+            lnk_file_metadata.changed =
+                DateTime::from_timestamp(file_metadata.ctime(), file_metadata.ctime_nsec() as u32);
+            lnk_file_metadata.inode = Some(file_metadata.ino());
+            lnk_file_metadata.uid = Some(file_metadata.uid());
+            lnk_file_metadata.gid = Some(file_metadata.gid());
+            lnk_file_metadata.mode = Some(file_metadata.mode());
+        }
+
+        Ok(lnk_file_metadata)
     }
 }
 
-impl Serialize for LnkFileMetaData {
-    fn serialize<S>(&self, serializer: S) -> std::result::Result<S::Ok, S::Error>
-    where
-        S: Serializer,
-    {
-        let mut state = serializer.serialize_struct("LnkFileMetaData", 4)?;
-        state.serialize_field("full_path", &self.full_path)?;
-        state.serialize_field(
-            "mtime",
-            &format!("{}", self.mtime.format("%Y-%m-%dT%H:%M:%SZ")),
-        )?;
-        state.serialize_field(
-            "atime",
-            &format!("{}", self.atime.format("%Y-%m-%dT%H:%M:%SZ")),
-        )?;
-        state.serialize_field(
-            "ctime",
-            &format!("{}", self.ctime.format("%Y-%m-%dT%H:%M:%SZ")),
-        )?;
-        state.end()
+/// Cryptographic hashes of the LNK file data.
+#[derive(Debug, Getters, Clone, Serialize, PartialEq, Eq)]
+#[getset(get = "pub with_prefix")]
+pub struct LnkHashes {
+    md5: String,
+    sha1: String,
+    sha256: String,
+}
+
+impl LnkHashes {
+    /// Hash the LNK file data.
+    pub fn from_buffer(buf: &[u8]) -> Self {
+        Self {
+            md5: format!("{:x}", Md5::digest(buf)),
+            sha1: format!("{:x}", Sha1::digest(buf)),
+            sha256: format!("{:x}", Sha256::digest(buf)),
+        }
     }
 }
 
+fn serialize_mode<S>(mode: &Option<u32>, serializer: S) -> std::result::Result<S::Ok, S::Error>
+where
+    S: Serializer,
+{
+    match mode {
+        Some(mode) => serializer.serialize_str(&format!("{:o}", mode)),
+        None => serializer.serialize_none(),
+    }
+}
+
+/// The absolute offsets of the structures in the parsed data (the LinkTargetIDList, LinkInfo and
+/// ExtraData blocks keep their own offsets).
+#[derive(Debug, Clone, Default, Getters)]
+#[getset(get = "pub with_prefix")]
+pub struct StructureOffsets {
+    pub shell_link_header: u64,
+    pub name_string: Option<u64>,
+    pub relative_path: Option<u64>,
+    pub working_dir: Option<u64>,
+    pub command_line_arguments: Option<u64>,
+    pub icon_location: Option<u64>,
+    pub extra_data: u64,
+}
+
 /// Reads LNK file and determine its parts then parses them
 #[derive(Debug, Serialize, Getters)]
 #[getset(get = "pub with_prefix")]
@@ -102,6 +170,8 @@ pub struct LNKParser {
     target_full_path: Option<String>,
     #[serde(skip_serializing_if = "Option::is_none")]
     lnk_file_metadata: Option<LnkFileMetaData>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    hashes: Option<LnkHashes>,
     shell_link_header: ShellLinkHeader,
     #[serde(skip_serializing_if = "Option::is_none")]
     link_target_id_list: Option<LinkTargetIDList>,
@@ -119,26 +189,32 @@ pub struct LNKParser {
     icon_location: Option<StringData>,
     #[serde(skip_serializing_if = "Option::is_none")]
     extra_data: Option<ExtraData>,
+    /// The number of bytes consumed by the LNK structures (up to the ExtraData terminal block).
+    #[serde(skip_serializing)]
+    parsed_size: u64,
+    /// The total size of the parsed data.
+    #[serde(skip_serializing)]
+    data_size: u64,
+    #[serde(skip_serializing)]
+    offsets: StructureOffsets,
 }
 
 #[inline]
 fn seek_string_data<R: Read + Seek>(r: &mut R, flags: &LinkFlags) -> Result<StringData, ReaderError> {
-    let offset = r.seek(SeekFrom::Current(0))?;
+    let offset = r.stream_position()?;
     let seek_pos = SeekFrom::Start(offset);
-    
+
     if flags.IsUnicode {
-        return Ok(StringData::from_reader(r)?)
+        StringData::from_reader(r)
     } else {
         r.seek(seek_pos)?;
-        
+
         match StringData::from_reader_cp1252(r) {
-            Ok(s) => {
-                return Ok(s)
-            },
+            Ok(s) => Ok(s),
             Err(_) => {
                 r.seek(seek_pos)?;
 
-                return Ok(StringData::from_reader_utf8(r)?);
+                StringData::from_reader_utf8(r)
             }
         }
     }
@@ -149,38 +225,72 @@ impl LNKParser {
     /// # Example
     /// ```
     ///# use lnk_parser::LNKParser;
-    /// fn main(){
-    ///     let lnk_file = LNKParser::from_path("sample.lnk");
-    ///     println!("{:?}", lnk_file);
-    /// }
+    /// let lnk_file = LNKParser::from_path("sample.lnk");
+    /// println!("{:?}", lnk_file);
     /// ```
     pub fn from_path(path: &str) -> Result<Self, ReaderError> {
+        Self::from_path_with_validation(path, true)
+    }
+    /// Parse LNK file from path without validating the ShellLinkHeader signature and CLSID.
+    pub fn from_path_unchecked(path: &str) -> Result<Self, ReaderError> {
+        Self::from_path_with_validation(path, false)
+    }
+
+    fn from_path_with_validation(path: &str, validate: bool) -> Result<Self, ReaderError> {
         let lnk_file_metadata = LnkFileMetaData::from_path(path)?;
-        let mut lnk_file_reader = fs::File::open(path)?;
-        let mut lnk_parser = Self::from_reader(&mut lnk_file_reader)?;
+        // LNK files are small, read the file once then hash and parse the data from memory
+        let data = fs::read(path)?;
+        let mut lnk_parser = if validate {
+            Self::from_buffer(&data)?
+        } else {
+            Self::from_buffer_unchecked(&data)?
+        };
         lnk_parser.lnk_file_metadata = Some(lnk_file_metadata);
+        lnk_parser.hashes = Some(LnkHashes::from_buffer(&data));
         Ok(lnk_parser)
     }
     /// Parse the LNK file data from buffer
     pub fn from_buffer(buf: &[u8]) -> Result<Self, ReaderError> {
         Self::from_reader(&mut Cursor::new(buf))
     }
+    /// Parse the LNK file data from buffer without validating the ShellLinkHeader signature and CLSID.
+    pub fn from_buffer_unchecked(buf: &[u8]) -> Result<Self, ReaderError> {
+        Self::from_reader_unchecked(&mut Cursor::new(buf))
+    }
     /// Parse LNK file from an instance that implement `Read` & `Seek` traits.
     /// # Example
     /// ```
     ///# use lnk_parser::LNKParser;
     /// use std::fs::File;
-    /// fn main(){
-    ///     // Open the LNK file
-    ///     let mut file = File::open("samples/WIN7/6.1_7601/network_share.lnk").unwrap();
-    ///     // Pass the `File` instance to `from_reader` function.
-    ///     // `std::fs::File` implements `Read` & `Seek` traits.
-    ///     let lnk_file = LNKParser::from_reader(&mut file);
-    ///     println!("{:?}", lnk_file);
-    /// }
+    /// // Open the LNK file
+    /// let mut file = File::open("samples/WIN7/6.1_7601/network_share.lnk").unwrap();
+    /// // Pass the `File` instance to `from_reader` function.
+    /// // `std::fs::File` implements `Read` & `Seek` traits.
+    /// let lnk_file = LNKParser::from_reader(&mut file);
+    /// println!("{:?}", lnk_file);
     /// ```
     pub fn from_reader<R: Read + Seek>(r: &mut R) -> Result<Self, ReaderError> {
+        let offset = r.stream_position()?;
         let shell_link_header = ShellLinkHeader::from_reader(r)?;
+        Self::from_header_and_reader(shell_link_header, offset, r)
+    }
+    /// Parse LNK file from an instance that implement `Read` & `Seek` traits without validating
+    /// the ShellLinkHeader signature and CLSID. Useful for forensic carving where the data could be partially corrupted.
+    pub fn from_reader_unchecked<R: Read + Seek>(r: &mut R) -> Result<Self, ReaderError> {
+        let offset = r.stream_position()?;
+        let shell_link_header = ShellLinkHeader::from_reader_unchecked(r)?;
+        Self::from_header_and_reader(shell_link_header, offset, r)
+    }
+
+    fn from_header_and_reader<R: Read + Seek>(
+        shell_link_header: ShellLinkHeader,
+        header_offset: u64,
+        r: &mut R,
+    ) -> Result<Self, ReaderError> {
+        let mut offsets = StructureOffsets {
+            shell_link_header: header_offset,
+            ..Default::default()
+        };
         let mut link_target_id_list = None;
         let mut link_info = None;
         let mut name_string = None;
@@ -198,26 +308,37 @@ impl LNKParser {
         }
 
         if shell_link_header.flags.HasName {
+            offsets.name_string = Some(r.stream_position()?);
             name_string = Some(seek_string_data(r, &shell_link_header.flags)?);
         }
 
         if shell_link_header.flags.HasRelativePath {
-            relative_path = Some(seek_string_data(r, &shell_link_header.flags)?)
+            offsets.relative_path = Some(r.stream_position()?);
+            relative_path = Some(seek_string_data(r, &shell_link_header.flags)?);
         }
 
         if shell_link_header.flags.HasWorkingDir {
-            working_dir = Some(seek_string_data(r, &shell_link_header.flags)?)
+            offsets.working_dir = Some(r.stream_position()?);
+            working_dir = Some(seek_string_data(r, &shell_link_header.flags)?);
         }
 
         if shell_link_header.flags.HasArguments {
-            command_line_arguments = Some(seek_string_data(r, &shell_link_header.flags)?)
+            offsets.command_line_arguments = Some(r.stream_position()?);
+            command_line_arguments = Some(seek_string_data(r, &shell_link_header.flags)?);
         }
 
         if shell_link_header.flags.HasIconLocation {
-            icon_location = Some(seek_string_data(r, &shell_link_header.flags)?)
+            offsets.icon_location = Some(r.stream_position()?);
+            icon_location = Some(seek_string_data(r, &shell_link_header.flags)?);
         }
 
+        offsets.extra_data = r.stream_position()?;
         let extra_data = ExtraData::from_reader(r).ok();
+        let parsed_size = match extra_data {
+            Some(_) => r.stream_position()?,
+            None => offsets.extra_data,
+        };
+        let data_size = r.seek(SeekFrom::End(0))?;
 
         let mut lnk_parser = Self {
             shell_link_header,
@@ -230,12 +351,26 @@ impl LNKParser {
             icon_location,
             extra_data,
             lnk_file_metadata: None,
+            hashes: None,
             target_full_path: None,
+            parsed_size,
+            data_size,
+            offsets,
         };
         lnk_parser.target_full_path = lnk_parser.path();
 
         Ok(lnk_parser)
     }
+
+    /// The number of bytes appended after the ExtraData terminal block (or after the last parsed structure).
+    pub fn overlay_size(&self) -> u64 {
+        self.data_size.saturating_sub(self.parsed_size)
+    }
+
+    /// Returns the most important fields as a typed `NormalizedRecord`.
+    pub fn to_normalized_record(&self) -> NormalizedRecord {
+        NormalizedRecord::from(self)
+    }
 }
 
 impl Path for LNKParser {
@@ -257,86 +392,29 @@ impl Path for LNKParser {
 }
 
 impl Normalize for LNKParser {
+    /// The fields of `NormalizedRecord` as strings. For compatibility with the previous versions,
+    /// the header timestamps are always set (`1601-01-01T00:00:00Z` if zero) and the MAC address
+    /// is `00:00:00:00:00:00` if there is no TrackerDataBlock.
     fn normalize(&self) -> HashMap<String, String> {
-        let mut fields: HashMap<String, String> = HashMap::new();
-        let mut target_hostname = String::new();
-
-        let target_full_path = match &self.path() {
-            Some(path) => path.to_owned(),
-            None => String::new(),
-        };
-
-        let target_modification_time = self.shell_link_header.mtime.to_string();
-        let target_access_time = self.shell_link_header.atime.to_string();
-        let target_creation_time = self.shell_link_header.ctime.to_string();
-
-        let target_size = self.shell_link_header.file_size.to_string();
-
-        match &self.extra_data {
-            Some(extra_data) => {
-                extra_data.extra_data_blocks.iter().find(|&edb| match edb {
-                    ExtraDataTypes::Tracker(tracker) => {
-                        target_hostname = tracker.machine_id.to_owned();
-                        true
-                    }
-                });
+        let mut fields = self
+            .to_normalized_record()
+            .fields()
+            .into_iter()
+            .map(|(name, value)| (name.to_string(), value))
+            .collect::<HashMap<String, String>>();
+        let header = &self.shell_link_header;
+        for (name, file_time) in [
+            ("target_modification_time", &header.mtime),
+            ("target_access_time", &header.atime),
+            ("target_creation_time", &header.ctime),
+        ] {
+            fields.insert(name.to_string(), file_time.to_string());
+        }
+        if let Some(mac_address) = fields.get_mut("mac_address") {
+            if mac_address.is_empty() {
+                *mac_address = String::from("00:00:00:00:00:00");
             }
-            None => {}
-        };
-
-        let lnk_full_path = match &self.lnk_file_metadata {
-            Some(lnk_file_metadata) => lnk_file_metadata.full_path.to_owned(),
-            None => String::new(),
-        };
-
-        let lnk_modification_time = match &self.lnk_file_metadata {
-            Some(lnk_file_metadata) => lnk_file_metadata
-                .mtime
-                .format("%Y-%m-%dT%H:%M:%SZ")
-                .to_string(),
-            None => String::new(),
-        };
-
-        let lnk_access_time = match &self.lnk_file_metadata {
-            Some(lnk_file_metadata) => lnk_file_metadata
-                .atime
-                .format("%Y-%m-%dT%H:%M:%SZ")
-                .to_string(),
-            None => String::new(),
-        };
-
-        let lnk_creation_time = match &self.lnk_file_metadata {
-            Some(lnk_file_metadata) => lnk_file_metadata
-                .ctime
-                .format("%Y-%m-%dT%H:%M:%SZ")
-                .to_string(),
-            None => String::new(),
-        };
-
-        fields.insert("target_full_path".to_string(), target_full_path);
-        fields.insert(
-            "target_modification_time".to_string(),
-            target_modification_time,
-        );
-
-        let mac_address = match &self.extra_data {
-            Some(ed) => match &ed.extra_data_blocks.get(0) {
-                Some(item) => match item {
-                    ExtraDataTypes::Tracker(t) => t.get_mac_address(),
-                },
-                None => String::from("00:00:00:00:00:00"),
-            },
-            None => String::from("00:00:00:00:00:00"),
-        };
-        fields.insert("target_access_time".to_string(), target_access_time);
-        fields.insert("target_creation_time".to_string(), target_creation_time);
-        fields.insert("target_size".to_string(), target_size);
-        fields.insert("target_hostname".to_string(), target_hostname);
-        fields.insert("lnk_full_path".to_string(), lnk_full_path);
-        fields.insert("lnk_modification_time".to_string(), lnk_modification_time);
-        fields.insert("lnk_access_time".to_string(), lnk_access_time);
-        fields.insert("lnk_creation_time".to_string(), lnk_creation_time);
-        fields.insert("mac_address".to_string(), mac_address);
+        }
         fields
     }
 }
@@ -42,6 +42,9 @@ pub struct LinkInfo {
     pub common_network_relative_link: Option<CommonNetworkRelativeLink>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub common_path_suffix: Option<String>,
+    /// The absolute offset of the structure in the parsed data, the offset fields are relative to it.
+    #[serde(skip_serializing)]
+    pub offset: u64,
 }
 
 impl LinkInfo {
@@ -50,6 +53,7 @@ impl LinkInfo {
     }
 
     pub fn from_reader<R: Read + Seek>(r: &mut R) -> Result<Self, ReaderError> {
+        let offset = r.stream_position()?;
         let size = r.read_u32::<LittleEndian>()?;
         let mut link_info_data = vec![0; (size - 4) as usize];
         r.read_exact(&mut link_info_data)?;
@@ -146,6 +150,7 @@ impl LinkInfo {
             local_base_path,
             common_network_relative_link,
             common_path_suffix,
+            offset,
         })
     }
 }
@@ -1,38 +1,170 @@
+//! [LinkTargetIDList](https://docs.microsoft.com/en-us/openspecs/windows_protocols/ms-shllink/881d7a83-07a5-4702-93e3-f9fc34c3e1e4) related structs
+
 use std::io::{Read, Cursor, Seek};
-use byteorder::{LittleEndian, ReadBytesExt};
+use byteorder::{ByteOrder, LittleEndian, ReadBytesExt};
+use chrono::{DateTime, NaiveDate, Utc};
+use getset::Getters;
 use winparsingtools::{
     traits::Path,
-    structs::shell_items::{IDList, ShellItem}, ReaderError
+    structs::{Guid, shell_items::{IDList, Name, ShellItem, ShellItemTypes}}, ReaderError
 };
 use serde::Serialize;
 
+/// The signature of the extension block (0xBEEF0004) that holds the MFT reference and timestamps of a file entry.
+const EXTENSION_BLOCK_SIGNATURE: [u8; 4] = [0x04, 0x00, 0xEF, 0xBE];
+
 /// The [LinkTargetIDList](https://docs.microsoft.com/en-us/openspecs/windows_protocols/ms-shllink/881d7a83-07a5-4702-93e3-f9fc34c3e1e4) structure specifies the target of the link.
 #[derive(Debug, Serialize)]
 pub struct LinkTargetIDList {
     #[serde(skip_serializing)]
     pub size: u16,
-    pub id_list: IDList
+    pub id_list: IDList,
+    /// The absolute offset of the structure in the parsed data.
+    #[serde(skip_serializing)]
+    pub offset: u64,
+    /// The raw `IDList` bytes, used to locate the shell items.
+    #[serde(skip_serializing)]
+    data: Vec<u8>,
+}
+
+/// A shell item with its location in the parsed data.
+#[derive(Debug)]
+pub struct ShellItemEntry<'a> {
+    /// The absolute offset of the shell item.
+    pub offset: u64,
+    /// The shell item size in bytes (including the size field).
+    pub size: u16,
+    pub item: &'a ShellItem,
+    /// The raw bytes of the shell item.
+    pub data: &'a [u8],
+}
+
+/// Metadata extracted from a FileEntry shell item and its extension block (0xBEEF0004).
+#[derive(Debug, Serialize, Getters)]
+#[getset(get = "pub with_prefix")]
+pub struct FileEntryMetadata {
+    /// The index of the shell item in the IDList.
+    pub index: usize,
+    /// The absolute offset of the shell item.
+    pub offset: u64,
+    pub name: String,
+    pub is_file: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub mft_entry: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub sequence_number: Option<u16>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub mtime: Option<DateTime<Utc>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub ctime: Option<DateTime<Utc>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub atime: Option<DateTime<Utc>>,
 }
 
 impl LinkTargetIDList {
-    #[allow(dead_code)]
     pub fn from_buffer(buf: &[u8]) -> Result<Self, ReaderError> {
         Self::from_reader(&mut Cursor::new(buf))
     }
     pub fn from_reader<R: Read + Seek>(r: &mut R) -> Result<Self, ReaderError> {
+        let offset = r.stream_position()?;
         let size = r.read_u16::<LittleEndian>()?;
         let mut id_list_data = vec![0;size as usize];
         r.read_exact(&mut id_list_data)?;
         let id_list = IDList::from_buffer(&id_list_data)?;
         Ok(Self {
             size,
-            id_list
+            id_list,
+            offset,
+            data: id_list_data,
         })
     }
 
     pub fn items(&self) -> std::slice::Iter<'_, ShellItem> {
         self.id_list.items()
     }
+
+    /// Returns the shell items with their absolute offsets and raw bytes.
+    pub fn entries(&self) -> Vec<ShellItemEntry<'_>> {
+        let mut entries = vec![];
+        // the first shell item starts after the `IDListSize` field
+        let mut position = 0;
+        for item in self.items() {
+            let size = item.size as usize;
+            let data = match self.data.get(position..position + size) {
+                Some(data) => data,
+                None => break,
+            };
+            entries.push(ShellItemEntry {
+                offset: self.offset + 2 + position as u64,
+                size: item.size,
+                item,
+                data,
+            });
+            position += size;
+        }
+        entries
+    }
+
+    /// Returns the path segment contributed by each shell item.
+    pub fn path_segments(&self) -> Vec<String> {
+        self.items().map(|item| item.name()).collect()
+    }
+
+    /// Returns the MFT reference and timestamps of every FileEntry shell item.
+    pub fn file_entries(&self) -> Vec<FileEntryMetadata> {
+        self.entries()
+            .iter()
+            .enumerate()
+            .filter_map(|(index, entry)| match &entry.item.shell_item_data {
+                Some(ShellItemTypes::FileEntry(file_entry)) => {
+                    let mtime = entry
+                        .data
+                        .get(8..12)
+                        .and_then(|b| dos_date_time(LittleEndian::read_u32(b)));
+                    let mut metadata = FileEntryMetadata {
+                        index,
+                        offset: entry.offset,
+                        name: entry.item.name(),
+                        is_file: file_entry.is_file,
+                        mft_entry: None,
+                        sequence_number: None,
+                        mtime,
+                        ctime: None,
+                        atime: None,
+                    };
+                    if let Some(block) = find_extension_block(entry.data) {
+                        metadata.ctime = block
+                            .get(8..12)
+                            .and_then(|b| dos_date_time(LittleEndian::read_u32(b)));
+                        metadata.atime = block
+                            .get(12..16)
+                            .and_then(|b| dos_date_time(LittleEndian::read_u32(b)));
+                        // The file reference is only available starting from version 7
+                        if LittleEndian::read_u16(&block[2..4]) >= 7 {
+                            if let Some(file_ref) = block.get(20..28) {
+                                metadata.mft_entry = Some(LittleEndian::read_u48(&file_ref[..6]));
+                                metadata.sequence_number =
+                                    Some(LittleEndian::read_u16(&file_ref[6..]));
+                            }
+                        }
+                    }
+                    Some(metadata)
+                }
+                _ => None,
+            })
+            .collect()
+    }
+
+    /// Returns the CLSID of the root shell item (for example `20D04FE0-3AEA-1069-A2D8-08002B30309D` for "My Computer").
+    pub fn root_clsid(&self) -> Option<&Guid> {
+        match self.items().next() {
+            Some(item) => match &item.shell_item_data {
+                Some(ShellItemTypes::Root(root)) => Some(&root.guid),
+                _ => None,
+            },
+            None => None,
+        }
+    }
 }
 
 impl Path for LinkTargetIDList {
@@ -40,3 +172,32 @@ impl Path for LinkTargetIDList {
         self.id_list.path()
     }
 }
+
+/// Returns the extension block (0xBEEF0004) bytes starting from its size field. `data` is the whole
+/// shell item, its last 2 bytes are the offset of the first extension block from the start of the item.
+fn find_extension_block(data: &[u8]) -> Option<&[u8]> {
+    let end = data.len().checked_sub(2)?;
+    let start = LittleEndian::read_u16(&data[end..]) as usize;
+    // size + version + signature + ctime + atime, the block can't overlap the offset field
+    let block = data.get(start..end)?;
+    if start < 4 || block.len() < 16 || block[4..8] != EXTENSION_BLOCK_SIGNATURE {
+        return None;
+    }
+    Some(block)
+}
+
+/// Convert a FAT date and time (date in the lower 16 bits) to `DateTime<Utc>`, zero or invalid values return `None`.
+pub(crate) fn dos_date_time(value: u32) -> Option<DateTime<Utc>> {
+    if value == 0 {
+        return None;
+    }
+    let day = value & 31;
+    let month = (value >> 5) & 15;
+    let year = ((value >> 9) & 127) as i32 + 1980;
+    let seconds = ((value >> 16) & 31) * 2;
+    let minutes = (value >> 21) & 63;
+    let hours = (value >> 27) & 31;
+    NaiveDate::from_ymd_opt(year, month, day)?
+        .and_hms_opt(hours, minutes, seconds)
+        .map(|date_time| DateTime::from_naive_utc_and_offset(date_time, Utc))
+}
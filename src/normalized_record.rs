@@ -0,0 +1,273 @@
+//! A flat, typed view of the most important fields of a parsed LNK file.
+
+use crate::extra_data::TrackerMoveStatus;
+use crate::shell_link_header::ShowCommandOptions;
+use crate::LNKParser;
+use chrono::{DateTime, NaiveDate, Utc};
+use serde::Serialize;
+use winparsingtools::{date_time::FileTime, traits::Path};
+
+/// The format used when the timestamps are converted to strings.
+pub const TIMESTAMP_FORMAT: &str = "%Y-%m-%dT%H:%M:%SZ";
+
+/// Declares `NormalizedRecord` with its fields listed once, the field names (`FIELDS`), types
+/// (`FIELD_TYPES`) and string values (`fields()`) are generated in the declaration order.
+macro_rules! normalized_record {
+    ($(#[$meta:meta])* pub struct $record:ident { $(pub $name:ident: $type:ty,)* }) => {
+        $(#[$meta])*
+        pub struct $record {
+            $(pub $name: $type,)*
+        }
+
+        impl $record {
+            /// The field names in the order they are serialized.
+            pub const FIELDS: &'static [&'static str] = &[$(stringify!($name)),*];
+
+            /// The field names with their value types and if they are nullable, in the same order as `FIELDS`.
+            pub const FIELD_TYPES: &'static [(&'static str, FieldType, bool)] = &[$((
+                stringify!($name),
+                <$type as FieldValue>::FIELD_TYPE,
+                <$type as FieldValue>::NULLABLE,
+            )),*];
+
+            /// Returns the fields as `(name, value)` pairs in the same order as `FIELDS`,
+            /// missing values are returned as empty strings.
+            pub fn fields(&self) -> Vec<(&'static str, String)> {
+                vec![$((stringify!($name), self.$name.to_field_string())),*]
+            }
+        }
+    };
+}
+
+normalized_record! {
+    /// The most important fields of a LNK file. The fields are serialized in the same order they are declared.
+    #[derive(Debug, Serialize, Clone, Default)]
+    pub struct NormalizedRecord {
+        pub target_full_path: Option<String>,
+        pub target_modification_time: Option<DateTime<Utc>>,
+        pub target_access_time: Option<DateTime<Utc>>,
+        pub target_creation_time: Option<DateTime<Utc>>,
+        pub target_size: u64,
+        pub target_hostname: Option<String>,
+        pub lnk_full_path: Option<String>,
+        pub lnk_modification_time: Option<DateTime<Utc>>,
+        pub lnk_access_time: Option<DateTime<Utc>>,
+        pub lnk_creation_time: Option<DateTime<Utc>>,
+        pub lnk_change_time: Option<DateTime<Utc>>,
+        pub lnk_size: Option<u64>,
+        pub lnk_md5: Option<String>,
+        pub lnk_sha1: Option<String>,
+        pub lnk_sha256: Option<String>,
+        pub mac_address: Option<String>,
+        pub name_string: Option<String>,
+        pub relative_path: Option<String>,
+        pub working_dir: Option<String>,
+        pub command_line_arguments: Option<String>,
+        pub icon_location: Option<String>,
+        pub drive_type: Option<String>,
+        pub drive_serial_number: Option<String>,
+        pub volume_label: Option<String>,
+        pub network_share_name: Option<String>,
+        pub network_device_name: Option<String>,
+        pub file_droid: Option<String>,
+        pub file_droid_birth: Option<String>,
+        pub volume_droid: Option<String>,
+        pub volume_droid_birth: Option<String>,
+        pub file_droid_time: Option<DateTime<Utc>>,
+        pub file_droid_clock_sequence: Option<u16>,
+        pub file_droid_birth_time: Option<DateTime<Utc>>,
+        pub file_droid_birth_clock_sequence: Option<u16>,
+        pub file_droid_birth_mac_address: Option<String>,
+        pub volume_droid_time: Option<DateTime<Utc>>,
+        pub volume_droid_clock_sequence: Option<u16>,
+        pub volume_droid_mac_address: Option<String>,
+        pub volume_droid_birth_time: Option<DateTime<Utc>>,
+        pub volume_droid_birth_clock_sequence: Option<u16>,
+        pub volume_droid_birth_mac_address: Option<String>,
+        pub tracker_move_status: Option<TrackerMoveStatus>,
+        pub tracker_volume_changed: Option<bool>,
+        pub tracker_file_droid_changed: Option<bool>,
+        pub tracker_machine_changed: Option<bool>,
+        pub show_command: ShowCommandOptions,
+        pub link_flags: Vec<String>,
+    }
+}
+
+/// The type of a `NormalizedRecord` field value, used to build typed schemas (e.g. the Parquet columns).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FieldType {
+    String,
+    Timestamp,
+    UInt64,
+    UInt16,
+    Boolean,
+    /// A list of strings.
+    List,
+}
+
+/// The value of a `NormalizedRecord` field.
+pub trait FieldValue {
+    const FIELD_TYPE: FieldType;
+    /// `true` if the value could be missing.
+    const NULLABLE: bool;
+
+    /// The value as a string, missing values are empty strings.
+    fn to_field_string(&self) -> String;
+}
+
+macro_rules! impl_display_field_value {
+    ($($type:ty => $field_type:ident),*) => {
+        $(
+            impl FieldValue for $type {
+                const FIELD_TYPE: FieldType = FieldType::$field_type;
+                const NULLABLE: bool = false;
+
+                fn to_field_string(&self) -> String {
+                    self.to_string()
+                }
+            }
+
+            impl FieldValue for Option<$type> {
+                const FIELD_TYPE: FieldType = FieldType::$field_type;
+                const NULLABLE: bool = true;
+
+                fn to_field_string(&self) -> String {
+                    self.as_ref().map(|value| value.to_string()).unwrap_or_default()
+                }
+            }
+        )*
+    };
+}
+
+impl_display_field_value!(
+    String => String,
+    u64 => UInt64,
+    u16 => UInt16,
+    bool => Boolean,
+    ShowCommandOptions => String
+);
+
+impl FieldValue for Option<DateTime<Utc>> {
+    const FIELD_TYPE: FieldType = FieldType::Timestamp;
+    const NULLABLE: bool = true;
+
+    fn to_field_string(&self) -> String {
+        match self {
+            Some(date_time) => date_time.format(TIMESTAMP_FORMAT).to_string(),
+            None => String::new(),
+        }
+    }
+}
+
+impl FieldValue for Option<TrackerMoveStatus> {
+    const FIELD_TYPE: FieldType = FieldType::String;
+    const NULLABLE: bool = true;
+
+    fn to_field_string(&self) -> String {
+        self.map(|status| format!("{:?}", status))
+            .unwrap_or_default()
+    }
+}
+
+impl FieldValue for Vec<String> {
+    const FIELD_TYPE: FieldType = FieldType::List;
+    const NULLABLE: bool = false;
+
+    fn to_field_string(&self) -> String {
+        self.join(",")
+    }
+}
+
+impl From<&LNKParser> for NormalizedRecord {
+    fn from(lnk: &LNKParser) -> Self {
+        let header = lnk.get_shell_link_header();
+        let tracker = lnk
+            .get_extra_data()
+            .as_ref()
+            .and_then(|extra_data| extra_data.tracker());
+        let file_droid_info = tracker.and_then(|t| t.file_droid_info());
+        let file_droid_birth_info = tracker.and_then(|t| t.file_droid_birth_info());
+        let volume_droid_info = tracker.and_then(|t| t.volume_droid_info());
+        let volume_droid_birth_info = tracker.and_then(|t| t.volume_droid_birth_info());
+        let move_analysis = tracker.map(|t| t.move_analysis());
+        let lnk_file_metadata = lnk.get_lnk_file_metadata().as_ref();
+        let hashes = lnk.get_hashes().as_ref();
+        let link_info = lnk.get_link_info().as_ref();
+        let volume_id = link_info.and_then(|link_info| link_info.volume_id.as_ref());
+        let network_link =
+            link_info.and_then(|link_info| link_info.common_network_relative_link.as_ref());
+
+        Self {
+            target_full_path: lnk.path(),
+            target_modification_time: file_time(&header.mtime),
+            target_access_time: file_time(&header.atime),
+            target_creation_time: file_time(&header.ctime),
+            target_size: header.file_size as u64,
+            target_hostname: tracker.map(|t| t.machine_id.to_owned()),
+            lnk_full_path: lnk_file_metadata.map(|m| m.get_full_path().to_owned()),
+            lnk_modification_time: lnk_file_metadata.and_then(|m| *m.get_modified()),
+            lnk_access_time: lnk_file_metadata.and_then(|m| *m.get_accessed()),
+            lnk_creation_time: lnk_file_metadata.and_then(|m| *m.get_born()),
+            lnk_change_time: lnk_file_metadata.and_then(|m| *m.get_changed()),
+            lnk_size: lnk_file_metadata.map(|m| *m.get_size()),
+            lnk_md5: hashes.map(|h| h.get_md5().to_owned()),
+            lnk_sha1: hashes.map(|h| h.get_sha1().to_owned()),
+            lnk_sha256: hashes.map(|h| h.get_sha256().to_owned()),
+            mac_address: tracker.map(|t| t.get_mac_address()),
+            name_string: lnk.get_name_string().as_ref().map(|s| s.to_string()),
+            relative_path: lnk.get_relative_path().as_ref().map(|s| s.to_string()),
+            working_dir: lnk.get_working_dir().as_ref().map(|s| s.to_string()),
+            command_line_arguments: lnk
+                .get_command_line_arguments()
+                .as_ref()
+                .map(|s| s.to_string()),
+            icon_location: lnk.get_icon_location().as_ref().map(|s| s.to_string()),
+            drive_type: volume_id.map(|v| format!("{:?}", v.get_drive_type())),
+            drive_serial_number: volume_id.map(|v| v.get_serial_number().to_owned()),
+            volume_label: volume_id.and_then(|v| v.get_volume_label().to_owned()),
+            network_share_name: network_link.and_then(|n| n.net_name.to_owned()),
+            network_device_name: network_link.and_then(|n| n.device_name.to_owned()),
+            file_droid: tracker.map(|t| t.file_droid.to_string()),
+            file_droid_birth: tracker.map(|t| t.file_droid_birth.to_string()),
+            volume_droid: tracker.map(|t| t.volume_droid.to_string()),
+            volume_droid_birth: tracker.map(|t| t.volume_droid_birth.to_string()),
+            file_droid_time: file_droid_info.as_ref().map(|d| d.time),
+            file_droid_clock_sequence: file_droid_info.as_ref().map(|d| d.clock_sequence),
+            file_droid_birth_time: file_droid_birth_info.as_ref().map(|d| d.time),
+            file_droid_birth_clock_sequence: file_droid_birth_info
+                .as_ref()
+                .map(|d| d.clock_sequence),
+            file_droid_birth_mac_address: file_droid_birth_info.map(|d| d.mac_address),
+            volume_droid_time: volume_droid_info.as_ref().map(|d| d.time),
+            volume_droid_clock_sequence: volume_droid_info.as_ref().map(|d| d.clock_sequence),
+            volume_droid_mac_address: volume_droid_info.map(|d| d.mac_address),
+            volume_droid_birth_time: volume_droid_birth_info.as_ref().map(|d| d.time),
+            volume_droid_birth_clock_sequence: volume_droid_birth_info
+                .as_ref()
+                .map(|d| d.clock_sequence),
+            volume_droid_birth_mac_address: volume_droid_birth_info.map(|d| d.mac_address),
+            tracker_move_status: move_analysis.map(|m| m.status),
+            tracker_volume_changed: move_analysis.map(|m| m.volume_changed),
+            tracker_file_droid_changed: move_analysis.map(|m| m.file_droid_changed),
+            tracker_machine_changed: move_analysis.map(|m| m.machine_changed),
+            show_command: header.show_command,
+            link_flags: header.flags.names().iter().map(|f| f.to_string()).collect(),
+        }
+    }
+}
+
+/// Convert `FileTime` to `DateTime<Utc>`, a zero FILETIME (1601-01-01) means the value is not set.
+pub(crate) fn file_time(file_time: &FileTime) -> Option<DateTime<Utc>> {
+    let date_time: DateTime<Utc> = file_time.into();
+    // Unwraping here is safe because the date is always valid.
+    let epoch = NaiveDate::from_ymd_opt(1601, 1, 1)
+        .unwrap()
+        .and_hms_opt(0, 0, 0)
+        .unwrap()
+        .and_utc();
+    if date_time == epoch {
+        None
+    } else {
+        Some(date_time)
+    }
+}
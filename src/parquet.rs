@@ -0,0 +1,130 @@
+//! Write normalized records to Apache Parquet files.
+//!
+//! The columns are the `NormalizedRecord` fields with typed values (timestamps are UTC timestamp
+//! columns, sizes and clock sequences are integers and the link flags are a list), followed by
+//! the target file attributes and the IDs of the findings and matching rules.
+//!
+//! The records are buffered and written in batches of `BATCH_SIZE` rows, so the memory usage
+//! is bounded by the row group size and not by the number of parsed files.
+
+use crate::{analysis::Finding, normalized_record::FieldType, LNKParser, NormalizedRecord};
+use ::parquet::{arrow::ArrowWriter, basic::Compression, file::properties::WriterProperties};
+use arrow_json::reader::{Decoder, ReaderBuilder};
+use arrow_schema::{DataType, Field, Schema, SchemaRef, TimeUnit};
+use serde_json::Value;
+use std::{
+    io::{Error, Result, Write},
+    sync::Arc,
+};
+
+/// The number of records decoded into a batch before it is passed to the Parquet writer.
+pub const BATCH_SIZE: usize = 1024;
+
+/// The maximum number of rows in a row group.
+pub const ROW_GROUP_SIZE: usize = 16 * 1024;
+
+/// Writes normalized records to a Parquet file.
+pub struct ParquetWriter<W: Write + Send> {
+    writer: ArrowWriter<W>,
+    decoder: Decoder,
+    buffered: usize,
+}
+
+impl<W: Write + Send> ParquetWriter<W> {
+    /// Write the Parquet data to `w`. The file is complete only after calling `finish`.
+    pub fn new(w: W) -> Result<Self> {
+        let schema = Self::schema();
+        let properties = WriterProperties::builder()
+            .set_compression(Compression::SNAPPY)
+            .set_max_row_group_size(ROW_GROUP_SIZE)
+            .build();
+        let writer =
+            ArrowWriter::try_new(w, schema.clone(), Some(properties)).map_err(parquet_error)?;
+        let decoder = ReaderBuilder::new(schema)
+            .with_batch_size(BATCH_SIZE)
+            .build_decoder()
+            .map_err(parquet_error)?;
+        Ok(Self {
+            writer,
+            decoder,
+            buffered: 0,
+        })
+    }
+
+    /// The schema of the written records.
+    pub fn schema() -> SchemaRef {
+        let timestamp = DataType::Timestamp(TimeUnit::Microsecond, Some("+00:00".into()));
+        let list = DataType::List(Arc::new(Field::new("item", DataType::Utf8, true)));
+        let mut fields = NormalizedRecord::FIELD_TYPES
+            .iter()
+            .map(|(name, field_type, nullable)| {
+                let data_type = match field_type {
+                    FieldType::String => DataType::Utf8,
+                    FieldType::Timestamp => timestamp.clone(),
+                    FieldType::UInt64 => DataType::UInt64,
+                    FieldType::UInt16 => DataType::UInt16,
+                    FieldType::Boolean => DataType::Boolean,
+                    FieldType::List => list.clone(),
+                };
+                Field::new(*name, data_type, *nullable)
+            })
+            .collect::<Vec<Field>>();
+        fields.push(Field::new("target_file_attributes", list.clone(), false));
+        fields.push(Field::new("findings", list.clone(), true));
+        fields.push(Field::new("rule_matches", list, true));
+        Arc::new(Schema::new(fields))
+    }
+
+    /// Add the normalized record of a parsed file. `findings` and `rule_matches` are null if not set.
+    pub fn write(
+        &mut self,
+        lnk: &LNKParser,
+        findings: Option<&[Finding]>,
+        rule_matches: Option<&[&str]>,
+    ) -> Result<()> {
+        let record = lnk.to_normalized_record();
+        let mut row = serde_json::to_value(&record)?;
+        // the unknown show commands are serialized as objects, use the string representation
+        row["show_command"] = record.show_command.to_string().into();
+        row["target_file_attributes"] = lnk
+            .get_shell_link_header()
+            .file_attr
+            .iter()
+            .map(|attr| format!("{:?}", attr))
+            .collect::<Vec<String>>()
+            .into();
+        row["findings"] = match findings {
+            Some(findings) => findings.iter().map(|f| f.id).collect::<Vec<&str>>().into(),
+            None => Value::Null,
+        };
+        row["rule_matches"] = match rule_matches {
+            Some(rule_matches) => rule_matches.to_vec().into(),
+            None => Value::Null,
+        };
+
+        self.decoder.serialize(&[row]).map_err(parquet_error)?;
+        self.buffered += 1;
+        if self.buffered >= BATCH_SIZE {
+            self.flush()?;
+        }
+        Ok(())
+    }
+
+    /// Write the remaining records and the file footer, returns the underlying writer.
+    pub fn finish(mut self) -> Result<W> {
+        self.flush()?;
+        self.writer.into_inner().map_err(parquet_error)
+    }
+
+    fn flush(&mut self) -> Result<()> {
+        if let Some(batch) = self.decoder.flush().map_err(parquet_error)? {
+            self.writer.write(&batch).map_err(parquet_error)?;
+        }
+        self.buffered = 0;
+        Ok(())
+    }
+}
+
+fn parquet_error<E: std::error::Error>(e: E) -> Error {
+    Error::other(format!("Parquet error: {}", e))
+}
@@ -0,0 +1,310 @@
+//! User-defined detection rules evaluated against the serialized `LNKParser`.
+//!
+//! Rules are loaded from YAML or TOML files. A rule has an ID and a condition, the condition
+//! is either a field match or a boolean combination (`all`, `any`, `not`) of other conditions.
+//!
+//! ```yaml
+//! rules:
+//!   - id: cmd_launching_powershell
+//!     description: cmd.exe used to launch PowerShell
+//!     condition:
+//!       all:
+//!         - field: target_full_path
+//!           ends_with: cmd.exe
+//!           ignore_case: true
+//!         - field: command_line_arguments
+//!           regex: "(?i)powershell(\\.exe)?\\s"
+//!         - not:
+//!             field: extra_data.tracker.machine_id
+//!             equals: build-server
+//! ```
+//!
+//! Fields are addressed with dot separated paths of the serialized `LNKParser` (the raw JSON output).
+//! Arrays are searched element by element, and when a key is not found in an object, the
+//! elements of its array members are searched instead, so `extra_data.tracker.machine_id` is
+//! the same as `extra_data.extra_data_blocks.tracker.machine_id`. A field match is true if any
+//! of the resolved values matches.
+
+use crate::LNKParser;
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::{
+    fmt::{self, Display},
+    fs,
+    io::{Error, ErrorKind, Result},
+    path::Path,
+};
+
+/// A scalar value in a rule file.
+#[derive(Debug, Deserialize, Clone, PartialEq)]
+#[serde(untagged)]
+pub enum RuleValue {
+    Bool(bool),
+    Integer(i64),
+    Float(f64),
+    String(String),
+}
+
+impl Display for RuleValue {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            RuleValue::Bool(value) => write!(f, "{}", value),
+            RuleValue::Integer(value) => write!(f, "{}", value),
+            RuleValue::Float(value) => write!(f, "{}", value),
+            RuleValue::String(value) => write!(f, "{}", value),
+        }
+    }
+}
+
+/// The comparison applied to the values of a field.
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Operator {
+    Equals(RuleValue),
+    Contains(String),
+    StartsWith(String),
+    EndsWith(String),
+    Regex(String),
+    LengthGreaterThan(usize),
+    LengthLessThan(usize),
+    GreaterThan(f64),
+    LessThan(f64),
+    /// `true` if the field must exist (and is not null), `false` if it must not.
+    Exists(bool),
+}
+
+/// Compare the values of a field with an operator.
+#[derive(Debug, Deserialize)]
+pub struct FieldMatch {
+    /// Dot separated path of the field (e.g. `shell_link_header.flags`).
+    pub field: String,
+    #[serde(flatten)]
+    pub operator: Operator,
+    /// Compare strings case insensitively (`equals`, `contains`, `starts_with` and `ends_with`).
+    #[serde(default)]
+    pub ignore_case: bool,
+    /// The compiled `regex` operator pattern.
+    #[serde(skip)]
+    regex: Option<Regex>,
+}
+
+/// A rule condition.
+#[derive(Debug, Deserialize)]
+#[serde(untagged)]
+pub enum Condition {
+    All { all: Vec<Condition> },
+    Any { any: Vec<Condition> },
+    Not { not: Box<Condition> },
+    Match(FieldMatch),
+}
+
+/// A detection rule.
+#[derive(Debug, Deserialize)]
+pub struct Rule {
+    pub id: String,
+    #[serde(default)]
+    pub description: Option<String>,
+    pub condition: Condition,
+}
+
+/// A rule that matched a parsed LNK file.
+#[derive(Debug, Serialize, Clone, PartialEq, Eq)]
+pub struct RuleMatch {
+    pub id: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+}
+
+/// A collection of detection rules.
+#[derive(Debug, Deserialize)]
+pub struct RuleSet {
+    pub rules: Vec<Rule>,
+}
+
+impl RuleSet {
+    /// Load the rules from a YAML string.
+    pub fn from_yaml(s: &str) -> Result<Self> {
+        let mut rule_set: Self =
+            serde_yaml::from_str(s).map_err(|e| invalid_rules(e.to_string()))?;
+        rule_set.compile()?;
+        Ok(rule_set)
+    }
+
+    /// Load the rules from a TOML string.
+    pub fn from_toml(s: &str) -> Result<Self> {
+        let mut rule_set: Self = toml::from_str(s).map_err(|e| invalid_rules(e.to_string()))?;
+        rule_set.compile()?;
+        Ok(rule_set)
+    }
+
+    /// Load the rules from a file, the format is determined by the extension (`.toml` for TOML, YAML otherwise).
+    pub fn from_path<P: AsRef<Path>>(path: P) -> Result<Self> {
+        let path = path.as_ref();
+        let data = fs::read_to_string(path)?;
+        match path.extension().and_then(|e| e.to_str()) {
+            Some(extension) if extension.eq_ignore_ascii_case("toml") => Self::from_toml(&data),
+            _ => Self::from_yaml(&data),
+        }
+    }
+
+    /// Compile the regular expressions of all the rules.
+    fn compile(&mut self) -> Result<()> {
+        for rule in self.rules.iter_mut() {
+            rule.condition
+                .compile()
+                .map_err(|e| invalid_rules(format!("rule '{}': {}", rule.id, e)))?;
+        }
+        Ok(())
+    }
+
+    /// Returns the rules that match the parsed LNK file. With the `deobfuscation` feature the
+    /// decoded arguments are matched as the `decoded_arguments` field.
+    pub fn evaluate(&self, lnk: &LNKParser) -> Vec<RuleMatch> {
+        let value = match serde_json::to_value(lnk) {
+            Ok(value) => value,
+            Err(_) => return vec![],
+        };
+        #[cfg(feature = "deobfuscation")]
+        let value = {
+            let mut value = value;
+            if let Some(decoded) = lnk.deobfuscated_arguments() {
+                value["decoded_arguments"] = serde_json::to_value(decoded).unwrap_or_default();
+            }
+            value
+        };
+        self.evaluate_value(&value)
+    }
+
+    /// Returns the rules that match a serialized `LNKParser`.
+    pub fn evaluate_value(&self, value: &Value) -> Vec<RuleMatch> {
+        self.rules
+            .iter()
+            .filter(|rule| rule.condition.evaluate(value))
+            .map(|rule| RuleMatch {
+                id: rule.id.to_owned(),
+                description: rule.description.to_owned(),
+            })
+            .collect()
+    }
+}
+
+impl Condition {
+    fn compile(&mut self) -> std::result::Result<(), regex::Error> {
+        match self {
+            Condition::All { all: conditions } | Condition::Any { any: conditions } => {
+                for condition in conditions.iter_mut() {
+                    condition.compile()?;
+                }
+                Ok(())
+            }
+            Condition::Not { not } => not.compile(),
+            Condition::Match(field_match) => {
+                if let Operator::Regex(pattern) = &field_match.operator {
+                    field_match.regex = Some(Regex::new(pattern)?);
+                }
+                Ok(())
+            }
+        }
+    }
+
+    /// Evaluate the condition against a serialized `LNKParser`.
+    pub fn evaluate(&self, value: &Value) -> bool {
+        match self {
+            Condition::All { all } => all.iter().all(|c| c.evaluate(value)),
+            Condition::Any { any } => any.iter().any(|c| c.evaluate(value)),
+            Condition::Not { not } => !not.evaluate(value),
+            Condition::Match(field_match) => field_match.evaluate(value),
+        }
+    }
+}
+
+impl FieldMatch {
+    /// Evaluate the match against a serialized `LNKParser`.
+    pub fn evaluate(&self, value: &Value) -> bool {
+        let values = resolve(value, &self.field);
+        if let Operator::Exists(exists) = self.operator {
+            return values.is_empty() != exists;
+        }
+        values.iter().any(|value| self.matches(value))
+    }
+
+    fn matches(&self, value: &Value) -> bool {
+        let normalize = |s: &str| {
+            if self.ignore_case {
+                s.to_lowercase()
+            } else {
+                s.to_string()
+            }
+        };
+        let text = normalize(&value_to_string(value));
+        match &self.operator {
+            Operator::Equals(expected) => text == normalize(&expected.to_string()),
+            Operator::Contains(expected) => text.contains(&normalize(expected)),
+            Operator::StartsWith(expected) => text.starts_with(&normalize(expected)),
+            Operator::EndsWith(expected) => text.ends_with(&normalize(expected)),
+            Operator::Regex(_) => self
+                .regex
+                .as_ref()
+                .is_some_and(|regex| regex.is_match(&value_to_string(value))),
+            Operator::LengthGreaterThan(length) => value_to_string(value).chars().count() > *length,
+            Operator::LengthLessThan(length) => value_to_string(value).chars().count() < *length,
+            Operator::GreaterThan(number) => value.as_f64().is_some_and(|v| v > *number),
+            Operator::LessThan(number) => value.as_f64().is_some_and(|v| v < *number),
+            Operator::Exists(_) => true,
+        }
+    }
+}
+
+/// Returns the non-null values at `path`, arrays are flattened.
+fn resolve<'a>(value: &'a Value, path: &str) -> Vec<&'a Value> {
+    let mut values = vec![value];
+    for key in path.split('.').filter(|key| !key.is_empty()) {
+        values = values
+            .into_iter()
+            .flat_map(|value| lookup(value, key))
+            .collect();
+    }
+    values
+        .into_iter()
+        .flat_map(flatten)
+        .filter(|value| !value.is_null())
+        .collect()
+}
+
+fn lookup<'a>(value: &'a Value, key: &str) -> Vec<&'a Value> {
+    match value {
+        Value::Array(items) => items.iter().flat_map(|item| lookup(item, key)).collect(),
+        Value::Object(map) => match map.get(key) {
+            Some(value) => vec![value],
+            // search the elements of the array members
+            None => map
+                .values()
+                .filter(|value| value.is_array())
+                .flat_map(|value| lookup(value, key))
+                .collect(),
+        },
+        _ => vec![],
+    }
+}
+
+fn flatten(value: &Value) -> Vec<&Value> {
+    match value {
+        Value::Array(items) => items.iter().flat_map(flatten).collect(),
+        _ => vec![value],
+    }
+}
+
+fn value_to_string(value: &Value) -> String {
+    match value {
+        Value::String(s) => s.to_owned(),
+        _ => value.to_string(),
+    }
+}
+
+fn invalid_rules(message: String) -> Error {
+    Error::new(
+        ErrorKind::InvalidData,
+        format!("invalid rules: {}", message),
+    )
+}
@@ -5,11 +5,16 @@ use getset::Getters;
 use serde::ser;
 use serde::Serialize;
 use std::fmt::{self, Display};
-use std::io::{Cursor, Read, Result};
+use std::io::{Cursor, Error, ErrorKind, Read, Result};
 use winparsingtools::date_time::FileTime;
 use winparsingtools::file_system::FileAttributesFlags;
 use winparsingtools::structs::Guid;
 
+/// The value of the `HeaderSize` field, it MUST be 0x0000004C.
+pub const HEADER_SIZE: u32 = 0x4C;
+/// The value of the `LinkCLSID` field, it MUST be `00021401-0000-0000-C000-000000000046`.
+pub const LINK_CLSID: &str = "00021401-0000-0000-C000-000000000046";
+
 /* #region  LinkFlags Struct Implementation */
 
 /// The [LinkFlags](https://docs.microsoft.com/en-us/openspecs/windows_protocols/ms-shllink/ae350202-3ba9-4790-9e9e-98935f4ee5af) structure defines bits that specify which shell link structures are present in the file format after the ShellLinkHeader structure
@@ -76,10 +81,9 @@ impl LinkFlags {
             KeepLocalIDListForUNCTarget: flags & 0x4 != 0,
         })
     }
-}
 
-impl Display for LinkFlags {
-    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    /// Returns the names of the flags that are set.
+    pub fn names(&self) -> Vec<&'static str> {
         let mut result = vec![];
         if self.HasLinkTargetIDList {
             result.push("HasLinkTargetIDList");
@@ -162,7 +166,13 @@ impl Display for LinkFlags {
         if self.KeepLocalIDListForUNCTarget {
             result.push("KeepLocalIDListForUNCTarget");
         }
-        write!(f, "{}", result.join(","))
+        result
+    }
+}
+
+impl Display for LinkFlags {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.names().join(","))
     }
 }
 
@@ -171,7 +181,7 @@ impl Serialize for LinkFlags {
     where
         S: ser::Serializer,
     {
-        serializer.serialize_some(&self.to_string().split(',').collect::<Vec<&str>>())
+        serializer.serialize_some(&self.names())
     }
 }
 
@@ -179,23 +189,71 @@ impl Serialize for LinkFlags {
 
 /* #region  ShellLinkHeader Struct Implementation */
 
-#[derive(Debug, Serialize)]
+/// The expected window state of an application launched by the link (the `SW_*` values of `ShowWindow`).
+/// The specification only defines `SHOWNORMAL`, `SHOWMAXIMIZED` and `SHOWMINNOACTIVE`, the rest are accepted by the shell
+/// and are kept because they are commonly used by malicious LNK files to hide the launched window.
+#[derive(Debug, Serialize, Clone, Copy, PartialEq, Eq, Default)]
 pub enum ShowCommandOptions {
+    HIDE,
+    /// The default, the specification treats the undefined values as `SHOWNORMAL`.
+    #[default]
     SHOWNORMAL,
+    SHOWMINIMIZED,
     SHOWMAXIMIZED,
+    SHOWNOACTIVATE,
+    SHOW,
+    MINIMIZE,
     SHOWMINNOACTIVE,
-    UNKOWN,
+    SHOWNA,
+    RESTORE,
+    SHOWDEFAULT,
+    FORCEMINIMIZE,
+    UNKNOWN(u32),
 }
 
 impl ShowCommandOptions {
-    fn from_u32(s: u32) -> ShowCommandOptions {
+    pub fn from_u32(s: u32) -> ShowCommandOptions {
         match s {
+            0 => ShowCommandOptions::HIDE,
             1 => ShowCommandOptions::SHOWNORMAL,
+            2 => ShowCommandOptions::SHOWMINIMIZED,
             3 => ShowCommandOptions::SHOWMAXIMIZED,
+            4 => ShowCommandOptions::SHOWNOACTIVATE,
+            5 => ShowCommandOptions::SHOW,
+            6 => ShowCommandOptions::MINIMIZE,
             7 => ShowCommandOptions::SHOWMINNOACTIVE,
-            _ => ShowCommandOptions::UNKOWN,
+            8 => ShowCommandOptions::SHOWNA,
+            9 => ShowCommandOptions::RESTORE,
+            10 => ShowCommandOptions::SHOWDEFAULT,
+            11 => ShowCommandOptions::FORCEMINIMIZE,
+            other => ShowCommandOptions::UNKNOWN(other),
         }
     }
+
+    /// Returns the raw value as it was stored in the file.
+    pub fn as_u32(&self) -> u32 {
+        match self {
+            ShowCommandOptions::HIDE => 0,
+            ShowCommandOptions::SHOWNORMAL => 1,
+            ShowCommandOptions::SHOWMINIMIZED => 2,
+            ShowCommandOptions::SHOWMAXIMIZED => 3,
+            ShowCommandOptions::SHOWNOACTIVATE => 4,
+            ShowCommandOptions::SHOW => 5,
+            ShowCommandOptions::MINIMIZE => 6,
+            ShowCommandOptions::SHOWMINNOACTIVE => 7,
+            ShowCommandOptions::SHOWNA => 8,
+            ShowCommandOptions::RESTORE => 9,
+            ShowCommandOptions::SHOWDEFAULT => 10,
+            ShowCommandOptions::FORCEMINIMIZE => 11,
+            ShowCommandOptions::UNKNOWN(other) => *other,
+        }
+    }
+}
+
+impl Display for ShowCommandOptions {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{:?}", self)
+    }
 }
 
 /// Represent HotKey data in the lnk file
@@ -271,28 +329,26 @@ impl Serialize for LinkHotKey {
 #[derive(Debug, Serialize, Getters)]
 #[getset(get = "pub with_prefix")]
 pub struct ShellLinkHeader {
-    #[serde(skip_serializing)]
     pub header_size: u32,
-    #[serde(skip_serializing)]
     pub guid: Guid,
-    #[serde(skip_serializing)]
     pub flags: LinkFlags,
     pub file_attr: Vec<FileAttributesFlags>,
     pub mtime: FileTime,
     pub atime: FileTime,
     pub ctime: FileTime,
     pub file_size: u32,
-    #[serde(skip_serializing)]
+    /// The raw IconIndex, the specification defines it as a signed integer, see `icon_index_signed`.
+    #[serde(serialize_with = "serialize_icon_index")]
     pub icon_index: u32,
-    #[serde(skip_serializing)]
-    sc: ShowCommandOptions,
+    pub show_command: ShowCommandOptions,
     pub hot_key: LinkHotKey,
-    #[serde(skip_serializing)]
     pub reserved0: u16,
-    #[serde(skip_serializing)]
     pub reserved1: u32,
-    #[serde(skip_serializing)]
     pub reserved2: u32,
+    /// Names of the reserved fields that are not zero. The specification requires them to be zero,
+    /// so any value here is a strong indicator that the file was crafted or tampered with.
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub non_zero_reserved_fields: Vec<&'static str>,
 }
 
 impl ShellLinkHeader {
@@ -300,7 +356,15 @@ impl ShellLinkHeader {
         Self::from_reader(&mut Cursor::new(buf))
     }
 
+    /// Parse the header and make sure that `header_size` and `guid` have the values required by the specification.
     pub fn from_reader<R: Read>(r: &mut R) -> Result<ShellLinkHeader> {
+        let header = Self::from_reader_unchecked(r)?;
+        header.validate()?;
+        Ok(header)
+    }
+
+    /// Parse the header without validating `header_size` and `guid`, useful when parsing carved data.
+    pub fn from_reader_unchecked<R: Read>(r: &mut R) -> Result<ShellLinkHeader> {
         let header_size = r.read_u32::<LittleEndian>()?;
         let guid = Guid::from_reader(r)?;
         let flags = LinkFlags::from_u32(r.read_u32::<BigEndian>()?)?;
@@ -310,12 +374,23 @@ impl ShellLinkHeader {
         let mtime = FileTime::new(r.read_u64::<LittleEndian>()?);
         let file_size = r.read_u32::<LittleEndian>()?;
         let icon_index = r.read_u32::<LittleEndian>()?;
-        let sc = ShowCommandOptions::from_u32(r.read_u32::<LittleEndian>()?);
+        let show_command = ShowCommandOptions::from_u32(r.read_u32::<LittleEndian>()?);
         let hot_key = LinkHotKey(r.read_u16::<LittleEndian>()?);
         let reserved0 = r.read_u16::<LittleEndian>()?;
         let reserved1 = r.read_u32::<LittleEndian>()?;
         let reserved2 = r.read_u32::<LittleEndian>()?;
 
+        let mut non_zero_reserved_fields = vec![];
+        if reserved0 != 0 {
+            non_zero_reserved_fields.push("reserved0");
+        }
+        if reserved1 != 0 {
+            non_zero_reserved_fields.push("reserved1");
+        }
+        if reserved2 != 0 {
+            non_zero_reserved_fields.push("reserved2");
+        }
+
         Ok(ShellLinkHeader {
             header_size,
             guid,
@@ -326,12 +401,68 @@ impl ShellLinkHeader {
             mtime,
             file_size,
             icon_index,
-            sc,
+            show_command,
             hot_key,
             reserved0,
             reserved1,
             reserved2,
+            non_zero_reserved_fields,
         })
     }
+
+    /// The IconIndex as the signed integer of the specification, negative values are icon resource IDs.
+    pub fn icon_index_signed(&self) -> i32 {
+        self.icon_index as i32
+    }
+
+    /// Check that `header_size` and `guid` have the values required by the specification.
+    pub fn validate(&self) -> Result<()> {
+        if self.header_size != HEADER_SIZE {
+            return Err(Error::new(
+                ErrorKind::InvalidData,
+                format!(
+                    "invalid ShellLinkHeader size: expected 0x{:X} found 0x{:X}",
+                    HEADER_SIZE, self.header_size
+                ),
+            ));
+        }
+        let guid = self.guid.to_string();
+        if guid != LINK_CLSID {
+            return Err(Error::new(
+                ErrorKind::InvalidData,
+                format!(
+                    "invalid ShellLinkHeader CLSID: expected '{}' found '{}'",
+                    LINK_CLSID, guid
+                ),
+            ));
+        }
+        Ok(())
+    }
+}
+
+fn serialize_icon_index<S>(icon_index: &u32, serializer: S) -> std::result::Result<S::Ok, S::Error>
+where
+    S: serde::Serializer,
+{
+    serializer.serialize_i32(*icon_index as i32)
+}
+
+/// Quick check if the buffer starts with a valid ShellLinkHeader signature (`header_size` and `guid`).
+/// # Example
+/// ```
+/// use lnk_parser::is_lnk;
+/// let data = std::fs::read("samples/WIN7/6.1_7601/network_share.lnk").unwrap();
+/// assert!(is_lnk(&data));
+/// assert!(!is_lnk(b"MZ"));
+/// ```
+pub fn is_lnk(buf: &[u8]) -> bool {
+    if buf.len() < 20 {
+        return false;
+    }
+    let r = &mut Cursor::new(buf);
+    match (r.read_u32::<LittleEndian>(), Guid::from_reader(r)) {
+        (Ok(header_size), Ok(guid)) => header_size == HEADER_SIZE && guid.to_string() == LINK_CLSID,
+        _ => false,
+    }
 }
 /* #endregion */
@@ -0,0 +1,364 @@
+//! Write parsed LNK files to a SQLite database.
+//!
+//! Every parsed file is a row in `lnk_files`, the other tables reference it with `lnk_file_id`:
+//!
+//! | Table               | Content                                                          |
+//! |---------------------|------------------------------------------------------------------|
+//! | `lnk_files`         | The LNK file metadata, hashes, header fields and StringData      |
+//! | `targets`           | The target path, times, size and volume or network share        |
+//! | `shell_items`       | The shell items of the LinkTargetIDList (with FileEntry metadata)|
+//! | `extra_data_blocks` | The ExtraData block headers and the strings of unknown blocks    |
+//! | `tracker`           | The TrackerDataBlock machine ID and droids                       |
+//! | `findings`          | The analysis findings and the matching detection rules           |
+//!
+//! The rows are committed every `COMMIT_INTERVAL` files and when `finish` is called. A file that
+//! is already in the database (same path and SHA-256) is replaced, writing the same files again
+//! doesn't duplicate them.
+//!
+//! ```sql
+//! SELECT l.full_path, t.full_path FROM lnk_files l
+//! JOIN targets t ON t.lnk_file_id = l.id
+//! JOIN tracker k ON k.lnk_file_id = l.id
+//! WHERE k.machine_id = 'win10';
+//! ```
+
+use crate::{analysis::Finding, LNKParser};
+use rusqlite::{params, Connection};
+use std::{
+    io::{Error, Result},
+    path::Path,
+};
+use winparsingtools::{structs::shell_items::Name, traits::Path as _};
+
+/// The written files are committed after this number of files.
+pub const COMMIT_INTERVAL: usize = 1000;
+
+const SCHEMA: &str = "
+CREATE TABLE IF NOT EXISTS lnk_files (
+    id INTEGER PRIMARY KEY,
+    full_path TEXT,
+    size INTEGER,
+    modified TEXT,
+    accessed TEXT,
+    changed TEXT,
+    born TEXT,
+    md5 TEXT,
+    sha1 TEXT,
+    sha256 TEXT,
+    fingerprint TEXT NOT NULL,
+    link_flags TEXT NOT NULL,
+    show_command TEXT NOT NULL,
+    icon_index INTEGER NOT NULL,
+    name_string TEXT,
+    relative_path TEXT,
+    working_dir TEXT,
+    command_line_arguments TEXT,
+    icon_location TEXT
+);
+CREATE TABLE IF NOT EXISTS targets (
+    id INTEGER PRIMARY KEY,
+    lnk_file_id INTEGER NOT NULL REFERENCES lnk_files(id) ON DELETE CASCADE,
+    full_path TEXT,
+    size INTEGER NOT NULL,
+    created TEXT,
+    accessed TEXT,
+    modified TEXT,
+    file_attributes TEXT NOT NULL,
+    drive_type TEXT,
+    drive_serial_number TEXT,
+    volume_label TEXT,
+    network_share_name TEXT,
+    network_device_name TEXT
+);
+CREATE TABLE IF NOT EXISTS shell_items (
+    id INTEGER PRIMARY KEY,
+    lnk_file_id INTEGER NOT NULL REFERENCES lnk_files(id) ON DELETE CASCADE,
+    position INTEGER NOT NULL,
+    offset INTEGER NOT NULL,
+    size INTEGER NOT NULL,
+    class_type INTEGER NOT NULL,
+    type TEXT,
+    name TEXT NOT NULL,
+    mft_entry INTEGER,
+    sequence_number INTEGER,
+    modified TEXT,
+    created TEXT,
+    accessed TEXT,
+    data TEXT
+);
+CREATE TABLE IF NOT EXISTS extra_data_blocks (
+    id INTEGER PRIMARY KEY,
+    lnk_file_id INTEGER NOT NULL REFERENCES lnk_files(id) ON DELETE CASCADE,
+    position INTEGER NOT NULL,
+    offset INTEGER NOT NULL,
+    size INTEGER NOT NULL,
+    signature INTEGER NOT NULL,
+    name TEXT NOT NULL,
+    strings TEXT
+);
+CREATE TABLE IF NOT EXISTS tracker (
+    id INTEGER PRIMARY KEY,
+    lnk_file_id INTEGER NOT NULL REFERENCES lnk_files(id) ON DELETE CASCADE,
+    machine_id TEXT NOT NULL,
+    mac_address TEXT,
+    file_droid TEXT NOT NULL,
+    file_droid_birth TEXT NOT NULL,
+    volume_droid TEXT NOT NULL,
+    volume_droid_birth TEXT NOT NULL,
+    file_droid_time TEXT,
+    file_droid_birth_time TEXT,
+    move_status TEXT NOT NULL
+);
+CREATE TABLE IF NOT EXISTS findings (
+    id INTEGER PRIMARY KEY,
+    lnk_file_id INTEGER NOT NULL REFERENCES lnk_files(id) ON DELETE CASCADE,
+    source TEXT NOT NULL,
+    finding_id TEXT NOT NULL,
+    severity TEXT,
+    description TEXT
+);
+CREATE UNIQUE INDEX IF NOT EXISTS lnk_files_full_path_sha256 ON lnk_files(full_path, sha256);
+CREATE INDEX IF NOT EXISTS lnk_files_md5 ON lnk_files(md5);
+CREATE INDEX IF NOT EXISTS lnk_files_fingerprint ON lnk_files(fingerprint);
+CREATE INDEX IF NOT EXISTS targets_lnk_file_id ON targets(lnk_file_id);
+CREATE INDEX IF NOT EXISTS targets_full_path ON targets(full_path);
+CREATE INDEX IF NOT EXISTS shell_items_lnk_file_id ON shell_items(lnk_file_id);
+CREATE INDEX IF NOT EXISTS shell_items_mft_entry ON shell_items(mft_entry);
+CREATE INDEX IF NOT EXISTS extra_data_blocks_lnk_file_id ON extra_data_blocks(lnk_file_id);
+CREATE INDEX IF NOT EXISTS tracker_lnk_file_id ON tracker(lnk_file_id);
+CREATE INDEX IF NOT EXISTS tracker_machine_id ON tracker(machine_id);
+CREATE INDEX IF NOT EXISTS findings_lnk_file_id ON findings(lnk_file_id);
+CREATE INDEX IF NOT EXISTS findings_finding_id ON findings(finding_id);
+";
+
+/// Writes parsed LNK files to a SQLite database.
+pub struct SqliteWriter {
+    connection: Connection,
+    /// The number of files written in the open transaction.
+    pending: usize,
+}
+
+impl SqliteWriter {
+    /// Open (or create) the database at `path` and create the tables if they don't exist.
+    pub fn create<P: AsRef<Path>>(path: P) -> Result<Self> {
+        let connection = Connection::open(path).map_err(sqlite_error)?;
+        Self::from_connection(connection)
+    }
+
+    /// Use an existing connection (e.g. an in-memory database).
+    pub fn from_connection(connection: Connection) -> Result<Self> {
+        connection
+            .execute_batch("PRAGMA foreign_keys = ON;")
+            .and_then(|_| connection.execute_batch(SCHEMA))
+            .and_then(|_| connection.execute_batch("BEGIN;"))
+            .map_err(sqlite_error)?;
+        Ok(Self {
+            connection,
+            pending: 0,
+        })
+    }
+
+    /// Insert a parsed file and its findings, returns the `lnk_files` row ID. The previous
+    /// files are committed first if `COMMIT_INTERVAL` files are pending.
+    pub fn write(&mut self, lnk: &LNKParser, findings: &[Finding]) -> Result<i64> {
+        if self.pending >= COMMIT_INTERVAL {
+            self.connection
+                .execute_batch("COMMIT; BEGIN;")
+                .map_err(sqlite_error)?;
+            self.pending = 0;
+        }
+        let lnk_file_id = self.insert(lnk, findings).map_err(sqlite_error)?;
+        self.pending += 1;
+        Ok(lnk_file_id)
+    }
+
+    /// Insert a matching detection rule of the `lnk_files` row `lnk_file_id`.
+    pub fn write_rule_match(
+        &mut self,
+        lnk_file_id: i64,
+        rule_id: &str,
+        description: Option<&str>,
+    ) -> Result<()> {
+        self.connection
+            .prepare_cached(
+                "INSERT INTO findings (lnk_file_id, source, finding_id, severity, description)
+                VALUES (?1, 'rule', ?2, NULL, ?3)",
+            )
+            .and_then(|mut statement| statement.execute(params![lnk_file_id, rule_id, description]))
+            .map_err(sqlite_error)?;
+        Ok(())
+    }
+
+    /// Commit the written rows and return the connection.
+    pub fn finish(self) -> Result<Connection> {
+        self.connection
+            .execute_batch("COMMIT;")
+            .map_err(sqlite_error)?;
+        Ok(self.connection)
+    }
+
+    fn insert(&mut self, lnk: &LNKParser, findings: &[Finding]) -> rusqlite::Result<i64> {
+        let record = lnk.to_normalized_record();
+        let header = lnk.get_shell_link_header();
+
+        // the rows of the other tables are deleted with the replaced file (ON DELETE CASCADE)
+        self.connection
+            .prepare_cached("DELETE FROM lnk_files WHERE full_path = ?1 AND sha256 = ?2")?
+            .execute(params![record.lnk_full_path, record.lnk_sha256])?;
+        self.connection
+            .prepare_cached(
+                "INSERT INTO lnk_files (full_path, size, modified, accessed, changed, born, md5, sha1,
+                sha256, fingerprint, link_flags, show_command, icon_index, name_string, relative_path,
+                working_dir, command_line_arguments, icon_location)
+                VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13, ?14, ?15, ?16, ?17, ?18)",
+            )?
+            .execute(params![
+                record.lnk_full_path,
+                // the size of the parsed data if the file was not parsed from a path
+                record.lnk_size.unwrap_or(*lnk.get_data_size()) as i64,
+                record.lnk_modification_time,
+                record.lnk_access_time,
+                record.lnk_change_time,
+                record.lnk_creation_time,
+                record.lnk_md5,
+                record.lnk_sha1,
+                record.lnk_sha256,
+                lnk.fingerprint().hash,
+                record.link_flags.join(","),
+                format!("{:?}", record.show_command),
+                header.icon_index_signed(),
+                record.name_string,
+                record.relative_path,
+                record.working_dir,
+                record.command_line_arguments,
+                record.icon_location,
+            ])?;
+        let lnk_file_id = self.connection.last_insert_rowid();
+
+        self.connection
+            .prepare_cached(
+                "INSERT INTO targets (lnk_file_id, full_path, size, created, accessed, modified,
+                file_attributes, drive_type, drive_serial_number, volume_label, network_share_name,
+                network_device_name)
+                VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12)",
+            )?
+            .execute(params![
+                lnk_file_id,
+                lnk.path(),
+                record.target_size,
+                record.target_creation_time,
+                record.target_access_time,
+                record.target_modification_time,
+                header
+                    .file_attr
+                    .iter()
+                    .map(|attr| format!("{:?}", attr))
+                    .collect::<Vec<String>>()
+                    .join(","),
+                record.drive_type,
+                record.drive_serial_number,
+                record.volume_label,
+                record.network_share_name,
+                record.network_device_name,
+            ])?;
+
+        if let Some(id_list) = lnk.get_link_target_id_list() {
+            let file_entries = id_list.file_entries();
+            for (position, entry) in id_list.entries().iter().enumerate() {
+                let data = serde_json::to_value(&entry.item.shell_item_data).ok();
+                // the shell item data is serialized as `{"type": {...}}`
+                let item_type = data
+                    .as_ref()
+                    .and_then(|data| data.as_object())
+                    .and_then(|data| data.keys().next().cloned());
+                let file_entry = file_entries.iter().find(|f| f.index == position);
+                self.connection
+                    .prepare_cached(
+                        "INSERT INTO shell_items (lnk_file_id, position, offset, size, class_type,
+                        type, name, mft_entry, sequence_number, modified, created, accessed, data)
+                        VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13)",
+                    )?
+                    .execute(params![
+                        lnk_file_id,
+                        position as i64,
+                        entry.offset as i64,
+                        entry.size,
+                        entry.item.class_type,
+                        item_type,
+                        entry.item.name(),
+                        file_entry.and_then(|f| f.mft_entry).map(|m| m as i64),
+                        file_entry.and_then(|f| f.sequence_number),
+                        file_entry.and_then(|f| f.mtime),
+                        file_entry.and_then(|f| f.ctime),
+                        file_entry.and_then(|f| f.atime),
+                        data.map(|data| data.to_string()),
+                    ])?;
+            }
+        }
+
+        if let Some(extra_data) = lnk.get_extra_data() {
+            for (position, block) in extra_data.blocks.iter().enumerate() {
+                self.connection
+                    .prepare_cached(
+                        "INSERT INTO extra_data_blocks (lnk_file_id, position, offset, size, signature,
+                        name, strings)
+                        VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)",
+                    )?
+                    .execute(params![
+                        lnk_file_id,
+                        position as i64,
+                        block.offset as i64,
+                        block.size,
+                        block.signature,
+                        block.name(),
+                        if block.strings.is_empty() {
+                            None
+                        } else {
+                            Some(block.strings.join("\n"))
+                        },
+                    ])?;
+            }
+            if let Some(tracker) = extra_data.tracker() {
+                self.connection
+                    .prepare_cached(
+                        "INSERT INTO tracker (lnk_file_id, machine_id, mac_address, file_droid,
+                        file_droid_birth, volume_droid, volume_droid_birth, file_droid_time,
+                        file_droid_birth_time, move_status)
+                        VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10)",
+                    )?
+                    .execute(params![
+                        lnk_file_id,
+                        tracker.machine_id,
+                        tracker.file_droid_info().map(|droid| droid.mac_address),
+                        tracker.file_droid.to_string(),
+                        tracker.file_droid_birth.to_string(),
+                        tracker.volume_droid.to_string(),
+                        tracker.volume_droid_birth.to_string(),
+                        record.file_droid_time,
+                        record.file_droid_birth_time,
+                        format!("{:?}", tracker.move_analysis().status),
+                    ])?;
+            }
+        }
+
+        for finding in findings {
+            self.connection
+                .prepare_cached(
+                    "INSERT INTO findings (lnk_file_id, source, finding_id, severity, description)
+                    VALUES (?1, 'analysis', ?2, ?3, ?4)",
+                )?
+                .execute(params![
+                    lnk_file_id,
+                    finding.id,
+                    finding.severity.to_string(),
+                    finding.rationale,
+                ])?;
+        }
+
+        Ok(lnk_file_id)
+    }
+}
+
+fn sqlite_error(e: rusqlite::Error) -> Error {
+    Error::other(format!("SQLite error: {}", e))
+}
@@ -0,0 +1,313 @@
+//! Timeline rows built from the timestamps of a LNK file.
+//!
+//! `bodyfile_entries` returns rows in the [Sleuth Kit bodyfile](https://wiki.sleuthkit.org/index.php?title=Body_file)
+//! format (`MD5|name|inode|mode_as_string|UID|GID|size|atime|mtime|ctime|crtime`),
+//! so the output can be consumed by `mactime` and the other super-timeline tools.
+//! `timeline_events` returns a row per timestamp found anywhere in the file, which can be
+//! written as TLN (`time|source|host|user|description`) or CSV.
+
+use crate::{
+    normalized_record::{file_time, TIMESTAMP_FORMAT},
+    LNKParser,
+};
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use std::fmt::{self, Display};
+use winparsingtools::{file_system::FileAttributesFlags, traits::Path};
+
+/// A line in a Sleuth Kit bodyfile.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BodyfileEntry {
+    /// `0` if the hash is not known.
+    pub md5: String,
+    pub name: String,
+    pub inode: u64,
+    /// The file type and permissions (e.g. `r/rrw-r--r--`).
+    pub mode: String,
+    pub uid: u32,
+    pub gid: u32,
+    pub size: u64,
+    pub atime: Option<DateTime<Utc>>,
+    pub mtime: Option<DateTime<Utc>>,
+    pub ctime: Option<DateTime<Utc>>,
+    pub crtime: Option<DateTime<Utc>>,
+}
+
+impl Display for BodyfileEntry {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "{}|{}|{}|{}|{}|{}|{}|{}|{}|{}|{}",
+            self.md5,
+            // the pipe is the field separator
+            self.name.replace('|', "_"),
+            self.inode,
+            self.mode,
+            self.uid,
+            self.gid,
+            self.size,
+            unix_time(&self.atime),
+            unix_time(&self.mtime),
+            unix_time(&self.ctime),
+            unix_time(&self.crtime)
+        )
+    }
+}
+
+impl LNKParser {
+    /// The bodyfile rows of the LNK file itself (if it was parsed from a path) and of its target.
+    ///
+    /// The target row has the target times stored in the ShellLinkHeader, its name is the target path
+    /// followed by the path of the LNK file (e.g. `C:\Windows\System32\cmd.exe (LNK: /cases/a.lnk)`).
+    pub fn bodyfile_entries(&self) -> Vec<BodyfileEntry> {
+        let mut entries = vec![];
+        let lnk_file_metadata = self.get_lnk_file_metadata().as_ref();
+        let md5 = self
+            .get_hashes()
+            .as_ref()
+            .map(|hashes| hashes.get_md5().to_owned())
+            .unwrap_or_else(|| String::from("0"));
+        let target = self
+            .path()
+            .unwrap_or_else(|| String::from("[unknown target]"));
+
+        if let Some(metadata) = lnk_file_metadata {
+            entries.push(BodyfileEntry {
+                md5,
+                name: format!("{} -> {}", metadata.get_full_path(), target),
+                inode: metadata.get_inode().unwrap_or(0),
+                mode: metadata
+                    .get_mode()
+                    .map(mode_to_string)
+                    .unwrap_or_else(|| String::from("r/r---------")),
+                uid: metadata.get_uid().unwrap_or(0),
+                gid: metadata.get_gid().unwrap_or(0),
+                size: *metadata.get_size(),
+                atime: *metadata.get_accessed(),
+                mtime: *metadata.get_modified(),
+                ctime: *metadata.get_changed(),
+                crtime: *metadata.get_born(),
+            });
+        }
+
+        let header = self.get_shell_link_header();
+        let is_directory = header
+            .file_attr
+            .iter()
+            .any(|attr| matches!(attr, FileAttributesFlags::DIRECTORY));
+        entries.push(BodyfileEntry {
+            md5: String::from("0"),
+            name: match lnk_file_metadata {
+                Some(metadata) => format!("{} (LNK: {})", target, metadata.get_full_path()),
+                None => target,
+            },
+            inode: 0,
+            mode: String::from(if is_directory {
+                "d/d---------"
+            } else {
+                "r/r---------"
+            }),
+            uid: 0,
+            gid: 0,
+            size: header.file_size as u64,
+            atime: file_time(&header.atime),
+            mtime: file_time(&header.mtime),
+            // the ShellLinkHeader does not store the MFT entry change time
+            ctime: None,
+            crtime: file_time(&header.ctime),
+        });
+
+        entries
+    }
+}
+
+/// Seconds since the Unix epoch, `0` if the time is not set.
+fn unix_time(time: &Option<DateTime<Utc>>) -> i64 {
+    time.map(|time| time.timestamp()).unwrap_or(0)
+}
+
+/// Convert `st_mode` to the `fls` mode string (e.g. `0o100644` to `r/rrw-r--r--`).
+fn mode_to_string(mode: u32) -> String {
+    let file_type = match mode & 0o170000 {
+        0o040000 => 'd',
+        0o120000 => 'l',
+        0o010000 => 'p',
+        0o020000 => 'c',
+        0o060000 => 'b',
+        0o140000 => 's',
+        0o100000 => 'r',
+        _ => '-',
+    };
+    let mut s = format!("{}/{}", file_type, file_type);
+    for shift in [6, 3, 0].iter() {
+        let bits = (mode >> shift) & 0o7;
+        s.push(if bits & 0o4 != 0 { 'r' } else { '-' });
+        s.push(if bits & 0o2 != 0 { 'w' } else { '-' });
+        s.push(if bits & 0o1 != 0 { 'x' } else { '-' });
+    }
+    s
+}
+
+/// A timestamp found in a LNK file.
+#[derive(Debug, Serialize, Clone, PartialEq, Eq)]
+pub struct TimelineEvent {
+    pub timestamp: DateTime<Utc>,
+    /// The structure the timestamp came from (e.g. `ShellLinkHeader`).
+    pub source: String,
+    /// What the timestamp is (e.g. `target creation time`).
+    pub description: String,
+    pub lnk_full_path: Option<String>,
+    pub target_full_path: Option<String>,
+    /// The NetBIOS name of the machine the target was on (from `TrackerDataBlock::machine_id`).
+    pub host: Option<String>,
+}
+
+impl TimelineEvent {
+    /// The names of the CSV columns, in the same order as `fields`.
+    pub const FIELDS: [&'static str; 6] = [
+        "timestamp",
+        "source",
+        "description",
+        "lnk_full_path",
+        "target_full_path",
+        "host",
+    ];
+
+    /// The values of the event as strings.
+    pub fn fields(&self) -> Vec<(&'static str, String)> {
+        let values = [
+            self.timestamp.format(TIMESTAMP_FORMAT).to_string(),
+            self.source.to_owned(),
+            self.description.to_owned(),
+            self.lnk_full_path.to_owned().unwrap_or_default(),
+            self.target_full_path.to_owned().unwrap_or_default(),
+            self.host.to_owned().unwrap_or_default(),
+        ];
+        Self::FIELDS.iter().copied().zip(values).collect()
+    }
+
+    /// The event as a TLN line (`time|source|host|user|description`).
+    pub fn to_tln(&self) -> String {
+        let mut description = format!("{} {}", self.source, self.description);
+        if let Some(target_full_path) = &self.target_full_path {
+            description.push_str(&format!(" - {}", target_full_path));
+        }
+        if let Some(lnk_full_path) = &self.lnk_full_path {
+            description.push_str(&format!(" (LNK: {})", lnk_full_path));
+        }
+        format!(
+            "{}|LNK|{}||{}",
+            self.timestamp.timestamp(),
+            self.host.to_owned().unwrap_or_default().replace('|', "_"),
+            description.replace('|', "_")
+        )
+    }
+}
+
+impl LNKParser {
+    /// One event per timestamp found in the file, sorted by time.
+    ///
+    /// The events include the target times in the ShellLinkHeader, the FAT times of every FileEntry
+    /// shell item and its extension block, the Tracker file droid times and the file system times of the LNK file.
+    pub fn timeline_events(&self) -> Vec<TimelineEvent> {
+        let mut times: Vec<(&str, String, Option<DateTime<Utc>>)> = vec![];
+        let header = self.get_shell_link_header();
+        times.push((
+            "ShellLinkHeader",
+            String::from("target creation time"),
+            file_time(&header.ctime),
+        ));
+        times.push((
+            "ShellLinkHeader",
+            String::from("target access time"),
+            file_time(&header.atime),
+        ));
+        times.push((
+            "ShellLinkHeader",
+            String::from("target modification time"),
+            file_time(&header.mtime),
+        ));
+
+        if let Some(id_list) = self.get_link_target_id_list() {
+            for entry in id_list.file_entries() {
+                times.push((
+                    "FileEntry",
+                    format!("'{}' modification time", entry.name),
+                    entry.mtime,
+                ));
+                times.push((
+                    "ExtensionBlock",
+                    format!("'{}' creation time", entry.name),
+                    entry.ctime,
+                ));
+                times.push((
+                    "ExtensionBlock",
+                    format!("'{}' access time", entry.name),
+                    entry.atime,
+                ));
+            }
+        }
+
+        let tracker = self
+            .get_extra_data()
+            .as_ref()
+            .and_then(|extra_data| extra_data.tracker());
+        if let Some(tracker) = tracker {
+            times.push((
+                "TrackerDataBlock",
+                String::from("file droid time"),
+                tracker.file_droid_info().map(|droid| droid.time),
+            ));
+            times.push((
+                "TrackerDataBlock",
+                String::from("file droid birth time"),
+                tracker.file_droid_birth_info().map(|droid| droid.time),
+            ));
+        }
+
+        let lnk_file_metadata = self.get_lnk_file_metadata().as_ref();
+        if let Some(metadata) = lnk_file_metadata {
+            times.push((
+                "LnkFileMetaData",
+                String::from("LNK file modification time"),
+                *metadata.get_modified(),
+            ));
+            times.push((
+                "LnkFileMetaData",
+                String::from("LNK file access time"),
+                *metadata.get_accessed(),
+            ));
+            times.push((
+                "LnkFileMetaData",
+                String::from("LNK file change time"),
+                *metadata.get_changed(),
+            ));
+            times.push((
+                "LnkFileMetaData",
+                String::from("LNK file creation time"),
+                *metadata.get_born(),
+            ));
+        }
+
+        let lnk_full_path = lnk_file_metadata.map(|metadata| metadata.get_full_path().to_owned());
+        let target_full_path = self.path();
+        let host = tracker
+            .map(|tracker| tracker.machine_id.to_owned())
+            .filter(|machine_id| !machine_id.is_empty());
+        let mut events = times
+            .into_iter()
+            .filter_map(|(source, description, timestamp)| {
+                Some(TimelineEvent {
+                    timestamp: timestamp?,
+                    source: source.to_string(),
+                    description,
+                    lnk_full_path: lnk_full_path.to_owned(),
+                    target_full_path: target_full_path.to_owned(),
+                    host: host.to_owned(),
+                })
+            })
+            .collect::<Vec<TimelineEvent>>();
+        events.sort_by_key(|event| event.timestamp);
+        events
+    }
+}
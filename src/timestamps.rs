@@ -0,0 +1,200 @@
+//! Consistency checks of the independent clocks stored in a LNK file.
+//!
+//! A LNK file records the target timestamps in the ShellLinkHeader (FILETIME), the FAT timestamps of
+//! every FileEntry shell item and its extension block, the creation time of the Tracker droids
+//! (UUIDv1) and the file system timestamps of the LNK file itself. The shell writes them at
+//! the same time, so they should agree. Disagreements could indicate timestomping or a crafted file.
+
+use crate::{
+    analysis::{Finding, Severity},
+    normalized_record::file_time,
+    LNKParser,
+};
+use chrono::{DateTime, Duration, Utc};
+
+/// FAT timestamps have a 2 seconds resolution.
+const FAT_RESOLUTION_SECONDS: i64 = 2;
+
+/// The tolerance used when comparing with the current time (clock skew between systems).
+const FUTURE_TOLERANCE_HOURS: i64 = 24;
+
+/// The tolerance used when comparing the target creation time with the LNK file creation time.
+const CREATION_TOLERANCE_MINUTES: i64 = 5;
+
+/// The UUID generator reserves time ranges in advance, so droid times could lag behind by hours.
+const DROID_TOLERANCE_HOURS: i64 = 24;
+
+impl LNKParser {
+    /// Cross-check the timestamps of the file and report the anomalies.
+    pub fn timestamp_anomalies(&self) -> Vec<Finding> {
+        self.timestamp_anomalies_at(Utc::now())
+    }
+
+    /// Same as `timestamp_anomalies` using `now` as the current time.
+    pub fn timestamp_anomalies_at(&self, now: DateTime<Utc>) -> Vec<Finding> {
+        let mut findings = vec![];
+        let header = self.get_shell_link_header();
+        let ctime = file_time(&header.ctime);
+        let atime = file_time(&header.atime);
+        let mtime = file_time(&header.mtime);
+        let lnk_file_metadata = self.get_lnk_file_metadata().as_ref();
+        let lnk_born = lnk_file_metadata.and_then(|m| *m.get_born());
+
+        // All-zero header times
+        let has_target = self.get_link_target_id_list().is_some() || self.get_link_info().is_some();
+        if has_target && ctime.is_none() && atime.is_none() && mtime.is_none() {
+            findings.push(Finding::new(
+                "zeroed_target_timestamps",
+                Severity::Low,
+                String::from("all the target timestamps in the ShellLinkHeader are zero, the file was likely not created by the Windows shell"),
+            ));
+        }
+
+        // Target created after the link
+        if let (Some(ctime), Some(lnk_born)) = (ctime, lnk_born) {
+            if ctime > lnk_born + Duration::minutes(CREATION_TOLERANCE_MINUTES) {
+                findings.push(Finding::new(
+                    "target_created_after_link",
+                    Severity::Low,
+                    format!(
+                        "the target creation time ({}) is after the creation time of the LNK file ({})",
+                        ctime, lnk_born
+                    ),
+                ));
+            }
+        }
+
+        // The target shell item, its FAT timestamps are a second record of the target times
+        let file_entries = self
+            .get_link_target_id_list()
+            .as_ref()
+            .map(|id_list| id_list.file_entries())
+            .unwrap_or_default();
+        let target_entry = file_entries.iter().rev().find(|entry| entry.is_file);
+        let tolerance = Duration::seconds(FAT_RESOLUTION_SECONDS);
+
+        // Identical header times and whole second FILETIMEs
+        let header_times = [ctime, atime, mtime]
+            .iter()
+            .flatten()
+            .copied()
+            .collect::<Vec<DateTime<Utc>>>();
+        let whole_seconds = !header_times.is_empty()
+            && header_times.iter().all(|t| t.timestamp_subsec_nanos() == 0);
+        // a newly created target has identical times too, only report them with whole seconds
+        // or when the shell item times of the target don't agree with each other
+        let shell_item_times_differ = target_entry
+            .and_then(|entry| Some((entry.ctime?, entry.mtime?)))
+            .is_some_and(|(ctime, mtime)| (ctime - mtime).abs() > tolerance);
+        if header_times.len() == 3
+            && header_times.iter().all(|t| *t == header_times[0])
+            && (whole_seconds || shell_item_times_differ)
+        {
+            findings.push(Finding::new(
+                "identical_target_timestamps",
+                Severity::Low,
+                format!(
+                    "the target creation, access and modification times are identical ({}) and {}",
+                    header_times[0],
+                    if whole_seconds {
+                        "have no sub-second precision"
+                    } else {
+                        "the creation and modification times of the target shell item differ"
+                    }
+                ),
+            ));
+        }
+        if whole_seconds {
+            findings.push(Finding::new(
+                "whole_second_timestamps",
+                Severity::Medium,
+                String::from("the target timestamps in the ShellLinkHeader have no sub-second precision, NTFS timestamps have 100 nanoseconds precision which suggests timestomping"),
+            ));
+        }
+
+        // The FAT timestamps of the target shell item ahead of the header FILETIMEs
+        if let Some(target_entry) = target_entry {
+            let pairs = [
+                ("modification", target_entry.mtime, mtime),
+                ("creation", target_entry.ctime, ctime),
+            ];
+            for (name, dos_time, file_time) in pairs.iter() {
+                if let (Some(dos_time), Some(file_time)) = (dos_time, file_time) {
+                    if *dos_time > *file_time + tolerance {
+                        findings.push(Finding::new(
+                            "dos_time_ahead_of_filetime",
+                            Severity::Medium,
+                            format!(
+                                "the {} time of the shell item '{}' ({}) is ahead of the ShellLinkHeader {} time ({})",
+                                name, target_entry.name, dos_time, name, file_time
+                            ),
+                        ));
+                    }
+                }
+            }
+        }
+
+        // Tracker droid created before the target
+        let tracker = self
+            .get_extra_data()
+            .as_ref()
+            .and_then(|extra_data| extra_data.tracker());
+        let droid_time = tracker
+            .and_then(|tracker| tracker.file_droid_info())
+            .map(|droid| droid.time);
+        if let (Some(droid_time), Some(ctime)) = (droid_time, ctime) {
+            if droid_time + Duration::hours(DROID_TOLERANCE_HOURS) < ctime {
+                findings.push(Finding::new(
+                    "droid_before_target_creation",
+                    Severity::Info,
+                    format!(
+                        "the file droid was created ({}) before the target ({})",
+                        droid_time, ctime
+                    ),
+                ));
+            }
+        }
+
+        // Future dates
+        let mut timestamps = vec![
+            ("ShellLinkHeader ctime", ctime),
+            ("ShellLinkHeader atime", atime),
+            ("ShellLinkHeader mtime", mtime),
+            ("file droid time", droid_time),
+            (
+                "file droid birth time",
+                tracker
+                    .and_then(|tracker| tracker.file_droid_birth_info())
+                    .map(|droid| droid.time),
+            ),
+            (
+                "LNK file modification time",
+                lnk_file_metadata.and_then(|m| *m.get_modified()),
+            ),
+            ("LNK file creation time", lnk_born),
+        ];
+        for entry in &file_entries {
+            timestamps.push(("shell item mtime", entry.mtime));
+            timestamps.push(("shell item ctime", entry.ctime));
+            timestamps.push(("shell item atime", entry.atime));
+        }
+        let future = timestamps
+            .iter()
+            .filter_map(|(name, time)| match time {
+                Some(time) if *time > now + Duration::hours(FUTURE_TOLERANCE_HOURS) => {
+                    Some(format!("{} ({})", name, time))
+                }
+                _ => None,
+            })
+            .collect::<Vec<String>>();
+        if !future.is_empty() {
+            findings.push(Finding::new(
+                "future_timestamp",
+                Severity::Medium,
+                format!("timestamps in the future: {}", future.join(", ")),
+            ));
+        }
+
+        findings
+    }
+}
